@@ -0,0 +1,225 @@
+//! Connectivity analysis over the network topology.
+//!
+//! The directed "peer A reported peer B" edges are treated as undirected links,
+//! since either side reporting the other means the two can reach each other.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::{report::ReportSection, topology::NetworkTopology};
+
+#[derive(Clone, Debug, Default)]
+pub struct NetworkAnalysis {
+    /// Number of peers in the topology
+    pub peer_count: usize,
+    /// Number of directed "reported" edges
+    pub edge_count: usize,
+    /// Connected components, largest first
+    pub components: Vec<Vec<String>>,
+    /// Peers whose removal splits their component
+    pub articulation_points: Vec<String>,
+    /// Stores (Degree -> Number of peers with that degree)
+    pub degree_distribution: BTreeMap<usize, usize>,
+    /// Largest shortest path found from the sampled peers (in hops)
+    pub diameter_estimate: usize,
+}
+
+impl NetworkAnalysis {
+    pub fn from_topology(topology: &NetworkTopology) -> Self {
+        let nodes: Vec<&String> = topology.nodes().into_iter().collect();
+        let index: HashMap<&String, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (*node, i))
+            .collect();
+
+        let mut neighbours = vec![BTreeSet::new(); nodes.len()];
+        for (source, targets) in topology.edges.iter() {
+            for target in targets.iter() {
+                let (s, t) = (index[source], index[target]);
+                neighbours[s].insert(t);
+                neighbours[t].insert(s);
+            }
+        }
+        let adjacency: Vec<Vec<usize>> = neighbours
+            .into_iter()
+            .map(|set| set.into_iter().collect())
+            .collect();
+
+        let mut degree_distribution = BTreeMap::new();
+        for links in adjacency.iter() {
+            *degree_distribution.entry(links.len()).or_insert(0) += 1;
+        }
+
+        let mut components: Vec<Vec<String>> = Self::components(&adjacency)
+            .into_iter()
+            .map(|component| component.into_iter().map(|i| nodes[i].clone()).collect())
+            .collect();
+        components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+
+        let articulation_points = Self::articulation_points(&adjacency)
+            .into_iter()
+            .map(|i| nodes[i].clone())
+            .collect();
+
+        let step = (adjacency.len() / MAX_DIAMETER_SAMPLES).max(1);
+        let diameter_estimate = (0..adjacency.len())
+            .step_by(step)
+            .map(|start| Self::eccentricity(&adjacency, start))
+            .max()
+            .unwrap_or(0);
+
+        NetworkAnalysis {
+            peer_count: nodes.len(),
+            edge_count: topology.edge_count(),
+            components,
+            articulation_points,
+            degree_distribution,
+            diameter_estimate,
+        }
+    }
+
+    /// Whether the overlay is split into more than one island.
+    pub fn is_partitioned(&self) -> bool {
+        self.components.len() > 1
+    }
+
+    /// Summarise the analysis as a report section.
+    pub fn to_report_section(&self) -> ReportSection {
+        let mut section = ReportSection::new("Network Analysis");
+
+        section.push("Peers", self.peer_count);
+        section.push("Reported edges", self.edge_count);
+        section.push("Connected components", self.components.len());
+        section.push(
+            "Largest component",
+            self.components.first().map(|c| c.len()).unwrap_or(0),
+        );
+        for (i, component) in self.components.iter().enumerate().skip(1) {
+            section.push(&format!("Island {}", i), component.join(" "));
+        }
+        section.push("Articulation points", self.articulation_points.len());
+        for peer in self.articulation_points.iter() {
+            section.push("Articulation point", peer);
+        }
+        section.push(
+            "Degree distribution",
+            self.degree_distribution
+                .iter()
+                .map(|(degree, count)| format!("{}:{}", degree, count))
+                .collect::<Vec<String>>()
+                .join(" "),
+        );
+        section.push("Diameter estimate (hops)", self.diameter_estimate);
+
+        section
+    }
+
+    /// Breadth first search over every unvisited node.
+    fn components(adjacency: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; adjacency.len()];
+        let mut components = Vec::new();
+
+        for start in 0..adjacency.len() {
+            if visited[start] {
+                continue;
+            }
+
+            visited[start] = true;
+            let mut component = vec![start];
+            let mut queue = VecDeque::from([start]);
+            while let Some(node) = queue.pop_front() {
+                for &next in adjacency[node].iter() {
+                    if !visited[next] {
+                        visited[next] = true;
+                        component.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        components
+    }
+
+    /// Iterative Tarjan search, so large overlays cannot overflow the stack.
+    fn articulation_points(adjacency: &[Vec<usize>]) -> BTreeSet<usize> {
+        let unvisited = usize::MAX;
+        let mut discovery = vec![unvisited; adjacency.len()];
+        let mut low = vec![0; adjacency.len()];
+        let mut parent = vec![unvisited; adjacency.len()];
+        let mut points = BTreeSet::new();
+        let mut timer = 0;
+
+        for root in 0..adjacency.len() {
+            if discovery[root] != unvisited {
+                continue;
+            }
+
+            discovery[root] = timer;
+            low[root] = timer;
+            timer += 1;
+            let mut root_children = 0;
+            // Stores (Node, Index of the next neighbour to visit)
+            let mut stack = vec![(root, 0)];
+
+            while let Some((node, next)) = stack.last().copied() {
+                if next < adjacency[node].len() {
+                    if let Some(top) = stack.last_mut() {
+                        top.1 += 1;
+                    }
+                    let neighbour = adjacency[node][next];
+                    if discovery[neighbour] == unvisited {
+                        parent[neighbour] = node;
+                        discovery[neighbour] = timer;
+                        low[neighbour] = timer;
+                        timer += 1;
+                        if node == root {
+                            root_children += 1;
+                        }
+                        stack.push((neighbour, 0));
+                    } else if neighbour != parent[node] {
+                        low[node] = low[node].min(discovery[neighbour]);
+                    }
+                } else {
+                    stack.pop();
+                    if let Some(&(up, _)) = stack.last() {
+                        low[up] = low[up].min(low[node]);
+                        if up != root && low[node] >= discovery[up] {
+                            points.insert(up);
+                        }
+                    }
+                }
+            }
+
+            if root_children > 1 {
+                points.insert(root);
+            }
+        }
+
+        points
+    }
+
+    /// Longest shortest path (in hops) from `start` to any reachable node.
+    fn eccentricity(adjacency: &[Vec<usize>], start: usize) -> usize {
+        let mut distance = vec![usize::MAX; adjacency.len()];
+        distance[start] = 0;
+        let mut farthest = 0;
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(node) = queue.pop_front() {
+            farthest = farthest.max(distance[node]);
+            for &next in adjacency[node].iter() {
+                if distance[next] == usize::MAX {
+                    distance[next] = distance[node] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        farthest
+    }
+}
+
+/// Upper bound on the number of breadth first searches run for the diameter estimate.
+const MAX_DIAMETER_SAMPLES: usize = 64;
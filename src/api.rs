@@ -0,0 +1,121 @@
+//! Local HTTP API to inspect a running controller and flush its results.
+//!
+//! | Method | Path             | Response                                        |
+//! | ------ | ---------------- | ----------------------------------------------- |
+//! | GET    | `/status`        | Run status                                      |
+//! | GET    | `/peers`         | Newly connected peers and how they were found   |
+//! | GET    | `/unreachable`   | Addresses that could not be dialed              |
+//! | GET    | `/routing-table` | Peers in the DHT routing table                  |
+//! | GET    | `/report`        | Network report sections as they stand           |
+//! | POST   | `/flush`         | Writes every result file without stopping       |
+//! | GET    | `/metrics`       | Prometheus metrics                              |
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use std::{net::SocketAddr, sync::Arc};
+use tokio::{net::TcpListener, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    error::CLIError,
+    handle::ControllerHandle,
+    metrics::Metrics,
+    report::NetworkReport,
+    state::{ControllerState, PeerState, RoutingEntry, RunStatus, UnreachableState},
+};
+
+type ApiResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+/// Shared by every request handler.
+#[derive(Clone)]
+struct ApiState {
+    handle: ControllerHandle,
+    metrics: Arc<Metrics>,
+}
+
+/// Bind the HTTP API to `addr` and serve it in the background until the task is aborted.
+pub(crate) async fn spawn(
+    addr: SocketAddr,
+    handle: ControllerHandle,
+    metrics: Arc<Metrics>,
+) -> Result<JoinHandle<()>, CLIError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| CLIError::resource_busy(format!("cannot bind the HTTP API to {}", addr), e))?;
+
+    let router = Router::new()
+        .route("/status", get(status))
+        .route("/peers", get(peers))
+        .route("/unreachable", get(unreachable))
+        .route("/routing-table", get(routing_table))
+        .route("/report", get(report))
+        .route("/flush", post(flush))
+        .route("/metrics", get(encode_metrics))
+        .with_state(ApiState { handle, metrics });
+
+    info!(addr = %addr, "Serving the HTTP API");
+    Ok(tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            error!(error = %e, "HTTP API stopped");
+        }
+    }))
+}
+
+async fn status(State(ApiState { handle, .. }): State<ApiState>) -> ApiResult<RunStatus> {
+    Ok(Json(state(&handle).await?.status))
+}
+
+async fn peers(State(ApiState { handle, .. }): State<ApiState>) -> ApiResult<Vec<PeerState>> {
+    Ok(Json(state(&handle).await?.peers))
+}
+
+async fn unreachable(
+    State(ApiState { handle, .. }): State<ApiState>,
+) -> ApiResult<Vec<UnreachableState>> {
+    Ok(Json(state(&handle).await?.unreachable))
+}
+
+async fn routing_table(
+    State(ApiState { handle, .. }): State<ApiState>,
+) -> ApiResult<Vec<RoutingEntry>> {
+    Ok(Json(state(&handle).await?.routing_table))
+}
+
+async fn report(State(ApiState { handle, .. }): State<ApiState>) -> ApiResult<NetworkReport> {
+    handle.snapshot().await.map(Json).ok_or_else(stopped)
+}
+
+async fn flush(
+    State(ApiState { handle, .. }): State<ApiState>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match handle.flush().await {
+        Some(Ok(())) => Ok(StatusCode::NO_CONTENT),
+        Some(Err(e)) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        None => Err(stopped()),
+    }
+}
+
+async fn state(handle: &ControllerHandle) -> Result<ControllerState, (StatusCode, String)> {
+    handle.state().await.ok_or_else(stopped)
+}
+
+async fn encode_metrics(State(ApiState { metrics, .. }): State<ApiState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, METRICS_CONTENT_TYPE)],
+        metrics.encode(),
+    )
+}
+
+fn stopped() -> (StatusCode, String) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        String::from("The controller has stopped."),
+    )
+}
+
+const METRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
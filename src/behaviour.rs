@@ -3,21 +3,29 @@
 use libp2p::{
     identify::{Behaviour as Identify, Config as IdentifyConfig, Event as IdentifyEvent},
     kad::{
-        store::MemoryStore, Behaviour as Kademlia, Config as KademliaConfig, Event as KademliaEvent,
+        store::MemoryStore, Behaviour as Kademlia, Config as KademliaConfig,
+        Event as KademliaEvent, QueryId,
     },
     mdns::{tokio::Behaviour as Mdns, Config as MdnsConfig, Event as MdnsEvent},
-    swarm::NetworkBehaviour,
+    ping::{Behaviour as Ping, Config as PingConfig, Event as PingEvent},
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour},
     Multiaddr, PeerId,
 };
+use std::time::Duration;
+use tracing::{info, warn};
 
-use crate::{config::Config, error::CLIError};
+use crate::{
+    config::{Config, Mode},
+    error::CLIError,
+};
 
 #[derive(NetworkBehaviour)]
 #[behaviour(to_swarm = "PeerNetworkEvent")]
 pub(crate) struct PeerNetworkBehaviour {
-    mdns: Mdns,
+    mdns: Toggle<Mdns>,
     identify: Identify,
     kad: Kademlia<MemoryStore>,
+    ping: Ping,
 }
 
 impl PeerNetworkBehaviour {
@@ -30,9 +38,28 @@ impl PeerNetworkBehaviour {
             config.keypair.public(),
         ));
 
-        // Configure mdns
-        let mdns =
-            Mdns::new(MdnsConfig::default(), local_peer_id).map_err(|_| CLIError::ResourceBusy)?;
+        // Configure mdns. Hosts without multicast (e.g. some containers) can still
+        // run the tool with mdns disabled, unless mdns is the only discovery mechanism.
+        let mdns = if config.mdns_enabled() {
+            let mdns_config = MdnsConfig {
+                ttl: Duration::from_secs(config.mdns_ttl),
+                query_interval: Duration::from_secs(config.mdns_query_interval),
+                enable_ipv6: config.mdns_ipv6,
+            };
+            match Mdns::new(mdns_config, local_peer_id) {
+                Ok(mdns) => Some(mdns),
+                Err(e) if config.mode == Mode::LanSurvey => {
+                    return Err(CLIError::resource_busy("cannot start mdns", e))
+                }
+                Err(e) => {
+                    warn!(error = %e, "Failed to start mdns, continuing without it");
+                    None
+                }
+            }
+        } else {
+            info!("Mdns is disabled");
+            None
+        };
 
         // Configure kad
         let mut kad = Kademlia::<MemoryStore>::with_config(
@@ -44,10 +71,14 @@ impl PeerNetworkBehaviour {
         // Run kad in client mode
         kad.set_mode(Some(libp2p::kad::Mode::Client));
 
+        // Configure ping to measure round-trip times of open connections
+        let ping = Ping::new(PingConfig::new());
+
         Ok(Self {
-            mdns,
+            mdns: Toggle::from(mdns),
             kad,
             identify,
+            ping,
         })
     }
 }
@@ -57,6 +88,7 @@ pub(crate) enum PeerNetworkEvent {
     Kad(KademliaEvent),
     Mdns(MdnsEvent),
     Identify(IdentifyEvent),
+    Ping(PingEvent),
 }
 
 impl From<IdentifyEvent> for PeerNetworkEvent {
@@ -77,6 +109,12 @@ impl From<KademliaEvent> for PeerNetworkEvent {
     }
 }
 
+impl From<PingEvent> for PeerNetworkEvent {
+    fn from(event: PingEvent) -> Self {
+        Self::Ping(event)
+    }
+}
+
 impl PeerNetworkBehaviour {
     /// Remove a peer from DHT.
     pub fn remove_peer(&mut self, peer_id: PeerId) {
@@ -93,9 +131,40 @@ impl PeerNetworkBehaviour {
         self.kad.add_address(peer, address);
     }
 
+    /// Peers in the DHT routing table with their known addresses.
+    pub fn routing_table(&mut self) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        self.kad
+            .kbuckets()
+            .flat_map(|bucket| {
+                bucket
+                    .iter()
+                    .map(|entry| {
+                        (
+                            *entry.node.key.preimage(),
+                            entry.node.value.iter().cloned().collect(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// `(Bucket index, Number of entries)` of every non-empty Kademlia bucket.
+    pub fn bucket_sizes(&mut self) -> Vec<(u32, usize)> {
+        self.kad
+            .kbuckets()
+            .map(|bucket| {
+                (
+                    bucket.range().0.ilog2().unwrap_or_default(),
+                    bucket.num_entries(),
+                )
+            })
+            .collect()
+    }
+
     /// Query the network with a PeerId so as to discover
     /// other peers in the network.
-    pub fn get_closest_peers(&mut self, peer_id: PeerId) {
-        self.kad.get_closest_peers(peer_id);
+    pub fn get_closest_peers(&mut self, peer_id: PeerId) -> QueryId {
+        self.kad.get_closest_peers(peer_id)
     }
 }
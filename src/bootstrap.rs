@@ -0,0 +1,343 @@
+//! Bootstrap module to load and validate bootstrap addresses and to check
+//! every configured bootstrap node explicitly.
+
+use libp2p::{
+    identify::Info,
+    multiaddr::{self, Protocol},
+    swarm::ConnectionId,
+    Multiaddr, PeerId,
+};
+use std::{
+    fs,
+    future::Future,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tracing::{error, warn};
+use trust_dns_resolver::{error::ResolveError, TokioAsyncResolver};
+
+use crate::{error::CLIError, report::ReportSection};
+
+/// Validate every bootstrap address and report each invalid one. Fails on the first
+/// invalid address unless `skip_invalid` is set and at least one address is valid.
+pub fn validate_bootstrap_addrs(
+    addrs: &[String],
+    skip_invalid: bool,
+) -> Result<Vec<Multiaddr>, CLIError> {
+    let mut valid = Vec::new();
+    let mut first_error = None;
+
+    for addr in addrs.iter() {
+        match validate_bootstrap_addr(addr) {
+            Ok(multi_addr) => {
+                if !multi_addr
+                    .iter()
+                    .any(|protocol| matches!(protocol, Protocol::P2p(_)))
+                {
+                    warn!(
+                        addr = %addr,
+                        "Bootstrap address has no /p2p/ component, its PeerId cannot be verified"
+                    );
+                }
+                valid.push(multi_addr);
+            }
+            Err(e) => {
+                error!(error = %e, "Invalid bootstrap address");
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match first_error {
+        Some(e) if !skip_invalid || valid.is_empty() => Err(e),
+        _ => Ok(valid),
+    }
+}
+
+/// Validate a single bootstrap address, explaining precisely why it is rejected.
+pub fn validate_bootstrap_addr(addr: &str) -> Result<Multiaddr, CLIError> {
+    let invalid = |reason: String| CLIError::InvalidBootstrapAddr {
+        addr: addr.to_string(),
+        reason,
+    };
+
+    // Check the PeerId first, so a bad one is not reported as a generic parse error
+    let components: Vec<&str> = addr.split('/').collect();
+    if let Some(i) = components.iter().position(|component| *component == "p2p") {
+        match components.get(i + 1).filter(|peer_id| !peer_id.is_empty()) {
+            Some(peer_id) => {
+                PeerId::from_str(peer_id)
+                    .map_err(|e| invalid(format!("bad peer id `{}` ({})", peer_id, e)))?;
+            }
+            None => return Err(invalid(String::from("missing peer id after /p2p/"))),
+        }
+    }
+
+    let multi_addr: Multiaddr = addr.parse().map_err(|e| {
+        invalid(match e {
+            multiaddr::Error::UnknownProtocolString(protocol) => {
+                format!("unknown protocol `{}`", protocol)
+            }
+            e => format!("bad protocol ({})", e),
+        })
+    })?;
+
+    match multi_addr.iter().next() {
+        Some(Protocol::Dnsaddr(_)) => return Ok(multi_addr),
+        Some(
+            Protocol::Ip4(_)
+            | Protocol::Ip6(_)
+            | Protocol::Dns(_)
+            | Protocol::Dns4(_)
+            | Protocol::Dns6(_),
+        ) => {}
+        _ => {
+            return Err(invalid(String::from(
+                "must start with /ip4, /ip6, /dns, /dns4, /dns6 or /dnsaddr",
+            )))
+        }
+    }
+
+    if !multi_addr
+        .iter()
+        .any(|protocol| matches!(protocol, Protocol::Tcp(_) | Protocol::QuicV1))
+    {
+        return Err(invalid(String::from(
+            "missing transport, expected /tcp/<port> or /udp/<port>/quic-v1",
+        )));
+    }
+
+    Ok(multi_addr)
+}
+
+/// Read bootstrap addresses from a file with one address per line.
+/// Empty lines and lines starting with `#` are ignored.
+pub fn read_bootstrap_file(path: &str) -> Result<Vec<String>, CLIError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| CLIError::bootstrap_source(format!("cannot read {}", path), e))?;
+
+    Ok(contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// Resolve bootstrap addresses from the `_dnsaddr.<domain>` TXT records of `domain`,
+/// following nested `/dnsaddr/` entries up to `MAX_DNSADDR_DEPTH` levels deep.
+pub async fn resolve_dnsaddr(domain: &str) -> Result<Vec<String>, CLIError> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()
+        .map_err(|e| CLIError::bootstrap_source("cannot create DNS resolver", e))?;
+
+    resolve_dnsaddr_with(domain, |name| {
+        let resolver = resolver.clone();
+        async move {
+            let records = resolver.txt_lookup(name).await?;
+            Ok::<_, ResolveError>(records.iter().map(|record| record.to_string()).collect())
+        }
+    })
+    .await
+}
+
+/// Resolve `domain` like [`resolve_dnsaddr`], getting the TXT records of a name from `lookup`.
+/// A nested entry ending in `/p2p/<id>` only yields the addresses of that peer. Nested entries
+/// that cannot be resolved are skipped, only a failed lookup of `domain` itself is an error.
+pub async fn resolve_dnsaddr_with<F, Fut, E>(
+    domain: &str,
+    lookup: F,
+) -> Result<Vec<String>, CLIError>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Vec<String>, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut addrs = Vec::new();
+    // Stores (Domain, Nesting depth, PeerID the entries are limited to)
+    let mut pending: Vec<(String, usize, Option<String>)> = vec![(domain.to_string(), 0, None)];
+    while let Some((domain, depth, peer_id)) = pending.pop() {
+        let name = format!("_dnsaddr.{}", domain);
+        let entries = match lookup(name.clone()).await {
+            Ok(entries) => entries,
+            Err(e) if depth == 0 => {
+                return Err(CLIError::bootstrap_source(
+                    format!("cannot resolve {}", name),
+                    e,
+                ))
+            }
+            Err(e) => {
+                warn!(name = %name, error = %e, "Skipping nested dnsaddr that cannot be resolved");
+                continue;
+            }
+        };
+
+        for entry in entries.iter() {
+            if let Some(addr) = entry.strip_prefix("dnsaddr=") {
+                if let Some(peer_id) = &peer_id {
+                    if !addr.ends_with(&format!("/p2p/{}", peer_id)) {
+                        continue;
+                    }
+                }
+                match addr.strip_prefix("/dnsaddr/") {
+                    Some(nested) if depth < MAX_DNSADDR_DEPTH => {
+                        let nested_domain = nested.split('/').next().unwrap_or_default();
+                        let nested_peer_id = nested
+                            .split("/p2p/")
+                            .nth(1)
+                            .map(String::from)
+                            .or_else(|| peer_id.clone());
+                        pending.push((nested_domain.to_string(), depth + 1, nested_peer_id));
+                    }
+                    Some(_) => warn!(addr = %addr, "Ignoring dnsaddr, nesting too deep"),
+                    None => addrs.push(addr.to_string()),
+                }
+            }
+        }
+    }
+
+    Ok(addrs)
+}
+
+#[derive(Clone, Debug)]
+pub struct BootstrapNode {
+    /// Bootstrap multiaddress as configured
+    pub address: String,
+    /// PeerID of the node, from the address or the established connection
+    pub peer_id: Option<PeerId>,
+    /// Time taken to establish the connection
+    pub connect_latency: Option<Duration>,
+    /// Reason the dial failed, if it did
+    pub dial_error: Option<String>,
+    pub agent_version: Option<String>,
+    pub protocol_version: Option<String>,
+    /// Number of peers the whole lookup seeded from this node found, not only the peers
+    /// this node answered with
+    pub lookup_result_size: Option<usize>,
+    /// Time taken by the lookup seeded from this node
+    pub lookup_duration: Option<Duration>,
+    connection_id: Option<ConnectionId>,
+    dialed_at: Instant,
+}
+
+impl BootstrapNode {
+    pub fn is_connected(&self) -> bool {
+        self.connect_latency.is_some()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BootstrapHealth {
+    pub nodes: Vec<BootstrapNode>,
+}
+
+impl BootstrapHealth {
+    pub fn new() -> Self {
+        BootstrapHealth { nodes: Vec::new() }
+    }
+
+    /// Record a dial to a bootstrap node. `connection_id` is `None` if the dial
+    /// could not even be started, in which case `dial_error` says why.
+    pub fn on_dial(
+        &mut self,
+        address: String,
+        peer_id: Option<PeerId>,
+        connection_id: Option<ConnectionId>,
+        dial_error: Option<String>,
+    ) {
+        self.nodes.push(BootstrapNode {
+            address,
+            peer_id,
+            connect_latency: None,
+            dial_error,
+            agent_version: None,
+            protocol_version: None,
+            lookup_result_size: None,
+            lookup_duration: None,
+            connection_id,
+            dialed_at: Instant::now(),
+        });
+    }
+
+    pub fn on_connected(&mut self, connection_id: ConnectionId, peer_id: PeerId) {
+        if let Some(node) = self.node_by_connection(connection_id) {
+            node.peer_id = Some(peer_id);
+            node.connect_latency = Some(node.dialed_at.elapsed());
+        }
+    }
+
+    pub fn on_dial_error(&mut self, connection_id: ConnectionId, error: String) {
+        if let Some(node) = self.node_by_connection(connection_id) {
+            node.dial_error = Some(error);
+        }
+    }
+
+    pub fn on_identify(&mut self, peer_id: PeerId, info: &Info) {
+        for node in self.nodes_by_peer(peer_id) {
+            node.agent_version = Some(info.agent_version.clone());
+            node.protocol_version = Some(info.protocol_version.clone());
+        }
+    }
+
+    /// Record the first lookup seeded from a bootstrap node.
+    pub fn on_lookup(
+        &mut self,
+        peer_id: PeerId,
+        lookup_result_size: usize,
+        duration: Option<Duration>,
+    ) {
+        for node in self.nodes_by_peer(peer_id) {
+            if node.lookup_result_size.is_none() {
+                node.lookup_result_size = Some(lookup_result_size);
+                node.lookup_duration = duration;
+            }
+        }
+    }
+
+    /// Summarise the health of every bootstrap node as a report section.
+    pub fn to_report_section(&self) -> ReportSection {
+        let mut section = ReportSection::new("Bootstrap Nodes");
+        let connected = self.nodes.iter().filter(|node| node.is_connected()).count();
+
+        section.push("Connected", format!("{}/{}", connected, self.nodes.len()));
+        for node in self.nodes.iter() {
+            section.push("Address", &node.address);
+            section.push("  Connected", node.is_connected());
+            section.push("  PeerID", display(node.peer_id));
+            section.push(
+                "  Connect time (ms)",
+                display(node.connect_latency.map(|d| d.as_millis())),
+            );
+            section.push("  Agent version", display(node.agent_version.clone()));
+            section.push("  Protocol version", display(node.protocol_version.clone()));
+            section.push("  Lookup result size", display(node.lookup_result_size));
+            section.push(
+                "  Lookup time (ms)",
+                display(node.lookup_duration.map(|d| d.as_millis())),
+            );
+            if let Some(error) = &node.dial_error {
+                section.push("  Dial error", error);
+            }
+        }
+
+        section
+    }
+
+    fn node_by_connection(&mut self, connection_id: ConnectionId) -> Option<&mut BootstrapNode> {
+        self.nodes
+            .iter_mut()
+            .find(|node| node.connection_id == Some(connection_id))
+    }
+
+    fn nodes_by_peer(&mut self, peer_id: PeerId) -> impl Iterator<Item = &mut BootstrapNode> {
+        self.nodes
+            .iter_mut()
+            .filter(move |node| node.peer_id == Some(peer_id))
+    }
+}
+
+fn display<T: ToString>(value: Option<T>) -> String {
+    value.map_or(String::from("-"), |value| value.to_string())
+}
+
+/// Maximum number of nested `/dnsaddr/` lookups followed while resolving bootstrap nodes.
+const MAX_DNSADDR_DEPTH: usize = 3;
@@ -0,0 +1,175 @@
+//! Churn tracker to record peers joining and leaving the network over time.
+
+use chrono::{DateTime, Utc};
+use csv::Writer;
+use std::{collections::BTreeMap, fs::File, path::PathBuf};
+
+use crate::{config::TimestampFormat, error::CLIError, report::ReportSection, writer::Headers};
+
+#[derive(Clone, Debug)]
+pub struct ChurnEvent {
+    pub peer: String,
+    pub kind: ChurnEventKind,
+    pub source: ChurnSource,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChurnEventKind {
+    Joined,
+    Left,
+}
+
+impl ToString for ChurnEventKind {
+    fn to_string(&self) -> String {
+        match self {
+            ChurnEventKind::Joined => String::from("Joined"),
+            ChurnEventKind::Left => String::from("Left"),
+        }
+    }
+}
+
+/// The signal a churn event was derived from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChurnSource {
+    Connection,
+    Mdns,
+}
+
+impl ToString for ChurnSource {
+    fn to_string(&self) -> String {
+        match self {
+            ChurnSource::Connection => String::from("Connection"),
+            ChurnSource::Mdns => String::from("Mdns"),
+        }
+    }
+}
+
+pub struct ChurnTracker {
+    /// Every join/leave event in the order it was observed
+    pub events: Vec<ChurnEvent>,
+    /// Lengths of completed connection sessions (in ms)
+    pub session_lengths: Vec<u128>,
+    /// Stores (PeerID -> Session start)
+    open_sessions: BTreeMap<String, DateTime<Utc>>,
+    /// Time the tracking started at
+    started_at: DateTime<Utc>,
+}
+
+impl ChurnTracker {
+    pub fn new(started_at: DateTime<Utc>) -> Self {
+        ChurnTracker {
+            events: Vec::new(),
+            session_lengths: Vec::new(),
+            open_sessions: BTreeMap::new(),
+            started_at,
+        }
+    }
+
+    /// Record a peer joining. Connection joins open a new session.
+    pub fn record_join(&mut self, peer: String, source: ChurnSource, timestamp: DateTime<Utc>) {
+        if source == ChurnSource::Connection {
+            self.open_sessions.insert(peer.clone(), timestamp);
+        }
+
+        self.events.push(ChurnEvent {
+            peer,
+            kind: ChurnEventKind::Joined,
+            source,
+            timestamp,
+        });
+    }
+
+    /// Record a peer leaving. Connection leaves close the open session, if any.
+    pub fn record_leave(&mut self, peer: String, source: ChurnSource, timestamp: DateTime<Utc>) {
+        if source == ChurnSource::Connection {
+            if let Some(started) = self.open_sessions.remove(&peer) {
+                let length = (timestamp - started).num_milliseconds().max(0);
+                self.session_lengths.push(length as u128);
+            }
+        }
+
+        self.events.push(ChurnEvent {
+            peer,
+            kind: ChurnEventKind::Left,
+            source,
+            timestamp,
+        });
+    }
+
+    /// Summarise churn up to `now` as a report section.
+    pub fn to_report_section(&self, now: DateTime<Utc>) -> ReportSection {
+        let mut section = ReportSection::new("Churn");
+
+        let count = |kind: ChurnEventKind| {
+            self.events
+                .iter()
+                .filter(|event| event.kind == kind && event.source == ChurnSource::Connection)
+                .count()
+        };
+        let (joins, leaves) = (count(ChurnEventKind::Joined), count(ChurnEventKind::Left));
+        let minutes =
+            ((now - self.started_at).num_milliseconds() as f64 / 60_000.0).max(f64::EPSILON);
+
+        section.push("Joins", joins);
+        section.push("Leaves", leaves);
+        section.push(
+            "Mdns expiries",
+            self.events
+                .iter()
+                .filter(|event| {
+                    event.source == ChurnSource::Mdns && event.kind == ChurnEventKind::Left
+                })
+                .count(),
+        );
+        section.push(
+            "Churn rate (events/min)",
+            format!("{:.2}", (joins + leaves) as f64 / minutes),
+        );
+        section.push("Completed sessions", self.session_lengths.len());
+        section.push("Open sessions", self.open_sessions.len());
+
+        let mut lengths = self.session_lengths.clone();
+        lengths.sort();
+        if !lengths.is_empty() {
+            let mean = lengths.iter().sum::<u128>() / lengths.len() as u128;
+            section.push("Mean session length (ms)", mean);
+            section.push("Median session length (ms)", lengths[lengths.len() / 2]);
+            section.push("Shortest session (ms)", lengths[0]);
+            section.push("Longest session (ms)", lengths[lengths.len() - 1]);
+        }
+
+        section
+    }
+
+    // `write_to_csv` writes every churn event to `path` with timestamps in `format`
+    // when `config.operation_duration` has been covered.
+    pub fn write_to_csv(&self, path: PathBuf, format: TimestampFormat) -> Result<(), CLIError> {
+        let file = File::create(&path).map_err(|e| CLIError::writer(&path, e))?;
+        let mut writer = Writer::from_writer(file);
+
+        writer
+            .write_record([
+                Headers::Peer.to_string(),
+                Headers::Event.to_string(),
+                Headers::Source.to_string(),
+                Headers::Timestamp.to_string(),
+            ])
+            .map_err(|e| CLIError::writer(&path, e))?;
+
+        for event in self.events.iter() {
+            writer
+                .write_record(&[
+                    event.peer.clone(),
+                    event.kind.to_string(),
+                    event.source.to_string(),
+                    format.format(&event.timestamp),
+                ])
+                .map_err(|e| CLIError::writer(&path, e))?;
+        }
+
+        writer.flush().map_err(|e| CLIError::writer(&path, e))?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,12 @@
 //! Config module for the tool.
 
-use libp2p::identity::{self, Keypair};
+use chrono::{DateTime, SecondsFormat, Utc};
+use libp2p::{
+    identity::{self, Keypair},
+    multiaddr::Protocol,
+    Multiaddr,
+};
+use std::{net::SocketAddr, str::FromStr};
 
 #[derive(Clone)]
 pub struct Config {
@@ -18,12 +24,202 @@ pub struct Config {
     pub unreachable_csv: String,
     /// CSV file input for newly connected peers
     pub just_connected: String,
+    /// GraphViz DOT file output for the network topology
+    pub topology_dot: String,
+    /// GraphML file output for the network topology
+    pub topology_graphml: String,
+    /// Text file output for the network report sections
+    pub network_report: String,
+    /// CSV file output for peer join/leave events
+    pub churn_csv: String,
     /// Relay address for dialing  
     pub bootstrap_addr: Vec<String>,
-    /// Log Level Setting
+    /// Continue with the valid bootstrap addresses if some are invalid
+    pub skip_invalid_bootstrap: bool,
+    /// Log level of every target, set through -v and -q
     pub log_level: String,
+    /// Format of the log output
+    pub log_format: LogFormat,
+    /// Per-module log filter directives applied on top of the log level, e.g. libp2p_kad=debug
+    pub log_filter: Option<String>,
+    /// File to append the log output to instead of stdout
+    pub log_file: Option<String>,
     /// Time duration till the tool operates (in s)
     pub operation_duration: u64,
+    /// Operation mode of the tool
+    pub mode: Mode,
+    /// Number of random lookups to sample for the network size estimate
+    pub size_estimation_samples: usize,
+    /// Mdns setting
+    pub mdns: MdnsMode,
+    /// Mdns record time to live (in s)
+    pub mdns_ttl: u64,
+    /// Mdns query interval (in s)
+    pub mdns_query_interval: u64,
+    /// Whether mdns queries IPv6 instead of IPv4 interfaces
+    pub mdns_ipv6: bool,
+    /// Local address to serve the HTTP API on, disabled if unset
+    pub http_api: Option<SocketAddr>,
+    /// File to dump the Prometheus metrics to at the end of the run, disabled if unset
+    pub metrics_file: Option<String>,
+    /// Sinks the peer tables and report sections are written to
+    pub sinks: Vec<SinkKind>,
+    /// Append to existing CSV reports instead of replacing them
+    pub csv_append: bool,
+    /// Timestamp put into CSV report file names
+    pub csv_naming: CsvNaming,
+    /// Size a CSV report is rotated at (in bytes), disabled if unset
+    pub csv_max_size: Option<u64>,
+    /// Format of the timestamps in reports and the HTTP API
+    pub timestamp_format: TimestampFormat,
+    /// SQLite database every run and peer observation is kept in, disabled if unset
+    pub history_db: Option<String>,
+}
+
+impl Config {
+    /// Whether mdns should run. In `MdnsMode::Auto` mdns only runs when every
+    /// bootstrap address is on a private network, since it is noise on public ones.
+    pub fn mdns_enabled(&self) -> bool {
+        match self.mdns {
+            MdnsMode::Enabled => true,
+            MdnsMode::Disabled => false,
+            MdnsMode::Auto => {
+                self.mode == Mode::LanSurvey
+                    || self.bootstrap_addr.iter().all(|addr| is_private_addr(addr))
+            }
+        }
+    }
+}
+
+/// Operation modes supported by the tool
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Crawl the network and report on reachable and unreachable peers
+    NetworkReport,
+    /// Sample random DHT lookups until the network size can be estimated
+    EstimateNetworkSize,
+    /// Discover peers on the local network through mdns only, without bootstrap nodes
+    LanSurvey,
+}
+
+/// Mdns settings supported by the tool
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MdnsMode {
+    /// Enable mdns only if all bootstrap nodes are on a private network
+    Auto,
+    Enabled,
+    Disabled,
+}
+
+impl FromStr for MdnsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(MdnsMode::Auto),
+            "on" => Ok(MdnsMode::Enabled),
+            "off" => Ok(MdnsMode::Disabled),
+            _ => Err(format!(
+                "invalid mdns setting `{}`, expected auto, on or off",
+                s
+            )),
+        }
+    }
+}
+
+/// Report sinks supported by the tool
+#[derive(Clone, Debug, PartialEq)]
+pub enum SinkKind {
+    /// CSV files at `config.just_connected` and `config.unreachable_csv`
+    Csv,
+    /// A JSON document at the given path
+    Json(String),
+    /// A SQLite database at the given path
+    Sqlite(String),
+    /// A summary on the console
+    Stdout,
+}
+
+impl FromStr for SinkKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            None if s == "csv" => Ok(SinkKind::Csv),
+            None if s == "stdout" => Ok(SinkKind::Stdout),
+            Some(("json", path)) if !path.is_empty() => Ok(SinkKind::Json(path.to_string())),
+            Some(("sqlite", path)) if !path.is_empty() => Ok(SinkKind::Sqlite(path.to_string())),
+            _ => Err(format!(
+                "invalid sink `{}`, expected csv, stdout, json=<PATH> or sqlite=<PATH>",
+                s
+            )),
+        }
+    }
+}
+
+/// File naming schemes supported for CSV reports
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsvNaming {
+    /// Use the configured paths as they are
+    Plain,
+    /// Add the start time of the run, so every run writes new files
+    PerRun,
+    /// Add the start of the current interval (in s), so runs within one interval share files
+    Interval(u64),
+}
+
+/// Timestamp formats supported in reports, all timestamps are in UTC
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimestampFormat {
+    /// RFC 3339 with milliseconds, e.g. 2023-11-12T09:30:00.123Z
+    Rfc3339,
+    /// Unix timestamp (in ms)
+    EpochMillis,
+}
+
+impl TimestampFormat {
+    pub fn format(&self, timestamp: &DateTime<Utc>) -> String {
+        match self {
+            TimestampFormat::Rfc3339 => timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            TimestampFormat::EpochMillis => timestamp.timestamp_millis().to_string(),
+        }
+    }
+}
+
+impl FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            "epoch-ms" => Ok(TimestampFormat::EpochMillis),
+            _ => Err(format!(
+                "invalid timestamp format `{}`, expected rfc3339 or epoch-ms",
+                s
+            )),
+        }
+    }
+}
+
+/// Log output formats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line, with the fields of the event and its spans
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("invalid log format `{}`, expected text or json", s)),
+        }
+    }
 }
 
 /// For convenience, default values are predefined
@@ -34,12 +230,54 @@ impl Default for Config {
             bootstrap_period: 5,
             listening_port: 7072,
             operation_duration: 181,
+            mode: Mode::NetworkReport,
+            size_estimation_samples: 100,
+            mdns: MdnsMode::Auto,
+            mdns_ttl: 360,
+            mdns_query_interval: 300,
+            mdns_ipv6: false,
+            http_api: None,
+            metrics_file: None,
+            sinks: vec![SinkKind::Csv],
+            csv_append: false,
+            csv_naming: CsvNaming::Plain,
+            csv_max_size: None,
+            timestamp_format: TimestampFormat::Rfc3339,
+            history_db: None,
             log_level: String::from("info"),
+            log_format: LogFormat::Text,
+            log_filter: None,
+            log_file: None,
             keypair: identity::Keypair::generate_ed25519(),
             just_connected: String::from("/home/newly_connected.csv"),
+            topology_dot: String::from("/home/topology.dot"),
+            topology_graphml: String::from("/home/topology.graphml"),
+            network_report: String::from("/home/network_report.txt"),
+            churn_csv: String::from("/home/churn.csv"),
             protocol: String::from("/light-client-test/1.0.0"),
             unreachable_csv: String::from("/home/unreachable.csv"),
             bootstrap_addr: vec![String::from("/ip4/172.16.3.2/udp/39000/quic-v1")],
+            skip_invalid_bootstrap: false,
+        }
+    }
+}
+
+/// A utility helper to check whether a multiaddress points into a private,
+/// loopback or link-local network.
+fn is_private_addr(addr: &str) -> bool {
+    let addr = match addr.parse::<Multiaddr>() {
+        Ok(addr) => addr,
+        Err(_) => return false,
+    };
+
+    match addr.iter().next() {
+        Some(Protocol::Ip4(ip)) => ip.is_private() || ip.is_loopback() || ip.is_link_local(),
+        Some(Protocol::Ip6(ip)) => {
+            // Unique local (fc00::/7) and link-local (fe80::/10) ranges
+            ip.is_loopback()
+                || (ip.segments()[0] & 0xfe00) == 0xfc00
+                || (ip.segments()[0] & 0xffc0) == 0xfe80
         }
+        _ => false,
     }
 }
@@ -1,7 +1,12 @@
 //! Swarm controller implementation.
 
 use anyhow::Result;
-use futures::{future::Either, StreamExt};
+use chrono::{DateTime, Utc};
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    future::Either,
+    FutureExt, StreamExt,
+};
 use libp2p::{
     core::{muxing::StreamMuxerBox, transport::Boxed, upgrade},
     dns::tokio::Transport as TokioDnsConfig,
@@ -10,39 +15,87 @@ use libp2p::{
     mdns::Event as MdnsEvent,
     multiaddr::Protocol,
     multihash::Multihash,
-    noise, quic,
-    swarm::{DialError, SwarmEvent},
+    noise,
+    ping::Event as PingEvent,
+    quic,
+    swarm::{dial_opts::DialOpts, DialError, SwarmEvent},
     tcp::Config as TcpConfig,
     yamux, Multiaddr, PeerId, Swarm, Transport,
 };
-use log::{error, info};
 use rand::Rng;
 use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap},
     net::Ipv4Addr,
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
-    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    sync::Arc,
+    time::{Duration, Instant},
 };
 use tokio::time;
+use tracing::{debug, error, info, info_span, warn};
 
 use crate::{
+    analysis::NetworkAnalysis,
+    api,
     behaviour::{PeerNetworkBehaviour, PeerNetworkEvent},
-    config::Config,
+    bootstrap::{validate_bootstrap_addrs, BootstrapHealth},
+    churn::{ChurnSource, ChurnTracker},
+    config::{Config, Mode},
     error::CLIError,
-    writer::{CSVWriter, Status},
+    estimator::NetworkSizeEstimator,
+    events::ControllerEvent,
+    handle::{ControllerCommand, ControllerHandle},
+    history::{HistoryDb, Observation},
+    logging,
+    metrics::{dial_error_class, Metrics},
+    report::NetworkReport,
+    sink::{self, ReportSink},
+    state::{ControllerState, PeerState, RoutingEntry, RunStatus, UnreachableState},
+    summary::RunSummary,
+    topology::{GraphFormat, NetworkTopology},
+    writer::{read_cache, write_cache, CSVWriter, DiscoverySource, Status},
 };
 
 pub struct Controller {
     pub config: Config,
     pub writer: CSVWriter,
+    pub topology: NetworkTopology,
+    pub estimator: NetworkSizeEstimator,
+    pub churn: ChurnTracker,
+    pub bootstrap_health: BootstrapHealth,
+    pub metrics: Arc<Metrics>,
+    /// Runs and peer observations kept across runs, if `config.history_db` is set
+    pub history: Option<HistoryDb>,
+    /// Summary of the run, once it is over
+    pub summary: Option<RunSummary>,
     swarm: Swarm<PeerNetworkBehaviour>,
+    /// Stores (QueryId -> QueryContext)
+    queries: HashMap<kad::QueryId, QueryContext>,
+    /// Senders of every event stream handed out by `Controller::events`
+    subscribers: Vec<UnboundedSender<ControllerEvent>>,
+    /// Commands sent through every `ControllerHandle`
+    commands: UnboundedReceiver<ControllerCommand>,
+    command_sender: UnboundedSender<ControllerCommand>,
+    /// Sinks the results are written to, built from `config.sinks`
+    sinks: Vec<Box<dyn ReportSink>>,
+    /// Stores (Dial error class -> Number of failed dials)
+    dial_failures: BTreeMap<&'static str, usize>,
+}
+
+/// Bookkeeping for an outstanding `get_closest_peers` query.
+struct QueryContext {
+    /// Peer the query was issued on behalf of, if any
+    origin: Option<PeerId>,
+    /// Whether the query targets a random key and can be sampled for the size estimate
+    sampled: bool,
 }
 
 impl Controller {
     /// Setup a new Controller object.
     pub async fn new(config: Config) -> Result<Self, CLIError> {
         // build transport layer
-        let transport = Self::build_transport_layer(&config).map_err(|_| CLIError::ResourceBusy)?;
+        let transport = Self::build_transport_layer(&config)?;
 
         // build network behaviour
         let behaviour = PeerNetworkBehaviour::new(&config)?;
@@ -56,44 +109,128 @@ impl Controller {
         );
 
         let writer = CSVWriter::new()?;
-
-        Ok(Controller {
+        let history = match &config.history_db {
+            Some(path) => Some(HistoryDb::open(
+                Path::new(path),
+                &config,
+                Self::current_timestamp().timestamp_millis(),
+            )?),
+            None => None,
+        };
+        let (command_sender, commands) = mpsc::unbounded();
+
+        let mut controller = Controller {
             writer,
             config,
             swarm,
-        })
+            topology: NetworkTopology::new(),
+            estimator: NetworkSizeEstimator::new(),
+            churn: ChurnTracker::new(Self::current_timestamp()),
+            bootstrap_health: BootstrapHealth::new(),
+            metrics: Arc::new(Metrics::new()),
+            history,
+            summary: None,
+            queries: HashMap::new(),
+            subscribers: Vec::new(),
+            commands,
+            command_sender,
+            sinks: Vec::new(),
+            dial_failures: BTreeMap::new(),
+        };
+        for sink in sink::from_config(&controller.config) {
+            controller.add_sink(sink);
+        }
+
+        Ok(controller)
+    }
+
+    /// Write the results to `sink` as well as the sinks in `config.sinks`.
+    pub fn add_sink(&mut self, sink: Box<dyn ReportSink>) {
+        self.sinks.push(sink);
     }
 
-    /// Start the Swarm controller.
+    /// Subscribe to the events of this controller. The stream ends once the
+    /// controller has stopped, so subscribe before calling `run`.
+    pub fn events(&mut self) -> UnboundedReceiver<ControllerEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Get a handle to drive the controller from another task.
+    pub fn handle(&self) -> ControllerHandle {
+        ControllerHandle::new(self.command_sender.clone())
+    }
+
+    /// Start the Swarm controller and run it to completion.
     pub async fn start(mut self) -> Result<Self, CLIError> {
+        self.run().await?;
+
+        Ok(self)
+    }
+
+    /// Run the Swarm controller until `config.operation_duration` has been covered
+    /// or it is stopped through a `ControllerHandle`. The results are written either way.
+    /// Handles get no answer from the controller once this returns.
+    pub async fn run(&mut self) -> Result<(), CLIError> {
+        let result = self.serve().await;
+
+        // Drop the reply of every command still queued, so handles waiting on one get `None`
+        self.commands.close();
+        while let Some(Some(_)) = self.commands.next().now_or_never() {}
+
+        result
+    }
+
+    /// Listen, dial the bootstrap nodes and handle events until the run is over.
+    async fn serve(&mut self) -> Result<(), CLIError> {
+        // Surface every invalid bootstrap address before any network activity starts.
+        // A LAN survey relies on mdns alone.
+        let bootstrap_addrs = match self.config.mode {
+            Mode::LanSurvey => Vec::new(),
+            _ => validate_bootstrap_addrs(
+                &self.config.bootstrap_addr,
+                self.config.skip_invalid_bootstrap,
+            )?,
+        };
+
         // Set a listener for this swarm
-        let listening_addr: Multiaddr = format!(
+        let listening_addr = format!(
             "/ip4/{}/tcp/{}",
             Ipv4Addr::new(127, 0, 0, 1),
             self.config.listening_port
-        )
-        .parse()
-        .map_err(|_| CLIError::IdentityError)?;
-        self.swarm
-            .listen_on(listening_addr)
-            .map_err(|_| CLIError::ResourceBusy)?;
-
-        // Dial bootstrapped nodes
-        for bootstrap_addr in self.config.bootstrap_addr.iter() {
-            let bootstrap_node_addr: Multiaddr = bootstrap_addr
-                .parse()
-                .map_err(|_| CLIError::IdentityError)?;
-
-            let _ = self.swarm.dial(bootstrap_node_addr);
+        );
+        let listening_multi_addr: Multiaddr = listening_addr
+            .parse()
+            .map_err(|e| CLIError::identity(listening_addr.clone(), e))?;
+        self.swarm.listen_on(listening_multi_addr).map_err(|e| {
+            CLIError::resource_busy(format!("cannot listen on {}", listening_addr), e)
+        })?;
+
+        // Serve the HTTP API while the controller runs
+        let api = match self.config.http_api {
+            Some(addr) => Some(api::spawn(addr, self.handle(), self.metrics.clone()).await?),
+            None => None,
+        };
+
+        // Dial bootstrapped nodes.
+        for bootstrap_node_addr in bootstrap_addrs {
+            self.dial_bootstrap(bootstrap_node_addr);
         }
 
         // Start event loop.
-        let _ = self.start_event_loop().await;
+        let result = self.start_event_loop().await;
 
-        Ok(self)
+        // End every event stream and the HTTP API
+        self.subscribers.clear();
+        if let Some(api) = api {
+            api.abort();
+        }
+
+        result
     }
 
-    /// The main event handler for swarm. Works on Identify, Kad and Mdns.
+    /// The main event handler for swarm. Works on Identify, Kad, Mdns and Ping.
     /// 1. Identify: In this context, identifies peers with whom connection has been established.
     ///              Also asks the peer node to discover closest peers on DHT.
     /// 2. Kademlia: In this context, the peer discovery protocol where a dialled peer searches
@@ -102,148 +239,346 @@ impl Controller {
     ///          nodes in the local network even before `kad` protocol is triggered.
     ///          The mdns protocol allows the tool to start interacting with the network with little to no
     ///          prerequisite information of bootstrapped peers.
+    /// 4. Ping: In this context, measures the round-trip time of open connections.
     async fn start_event_loop(&mut self) -> Result<(), CLIError> {
         let current_instant = Instant::now();
         let mut bootstrap_interval =
             time::interval(Duration::from_secs(self.config.bootstrap_period));
+        // Ends the run even if no other event arrives, e.g. a LAN survey without peers
+        let deadline = time::sleep(Duration::from_secs(self.config.operation_duration));
+        tokio::pin!(deadline);
+        let mut deadline_reached = false;
+        let mut stopped = false;
 
         loop {
             tokio::select! {
                 event = self.swarm.next() => {
-                    match event.expect("Stream should be infinite.") {
-                        SwarmEvent::Behaviour(PeerNetworkEvent::Identify(event)) => match event {
-                            IdentifyEvent::Received { peer_id, info } => {
-                                info!("[Identify]: Received identify: Peer ID: {} Listen addrs: {:?} {:?}", peer_id, info.listen_addrs, info.observed_addr);
-                                info.listen_addrs
-                                    .into_iter()
-                                    .filter(|multi_addr| multi_addr.to_string().contains(Protocol::P2p(peer_id).tag()))
-                                    .for_each(|multi_addr| {
-                                        info!("[Task 3]: Resolve IP for newly connected peers.");
-                                        if let Ok(ip) = Self::get_peer_ip(&info.observed_addr) {
-                                            info!("[Task 2]: Log newly connected peers.");
-                                            self.writer.newly_connected_peer_cache.write().unwrap().entry(peer_id.to_string())
-                                                .or_insert((ip, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis().to_string()));
-                                        } else {
-                                            error!("[Swarm]: Failed to resolve peer IP.")
-                                        }
-                                        // Add the peer to DHT
-                                        self.swarm.behaviour_mut().add_address(&peer_id, multi_addr);
-                                        // Ask peer to discover more peers
-                                        self.swarm.behaviour_mut().get_closest_peers(peer_id);
-
-                                        for i in 1..=MAX_RECONSTRUCTED_PEERS {
-                                            if let Ok(reconstructed_peer_id) = Self::generate_random_peer(peer_id, i) {
-                                                self.swarm.behaviour_mut().get_closest_peers(reconstructed_peer_id);
+                    if let Some(event) = &event {
+                        self.metrics.record(event);
+                        if let SwarmEvent::Behaviour(event) = event {
+                            self.metrics.record_behaviour(event);
+                        }
+                    }
+                    match event {
+                        None => {
+                            // The swarm should never stop, stop early rather than spin on it
+                            error!("Swarm event stream ended unexpectedly, stopping the run");
+                            stopped = true;
+                        }
+                        Some(event) => match event {
+                            SwarmEvent::Behaviour(PeerNetworkEvent::Identify(event)) => match event {
+                                IdentifyEvent::Received { peer_id, info } => {
+                                    let _span = info_span!("peer", peer_id = %peer_id).entered();
+                                    info!(listen_addrs = ?info.listen_addrs, observed_addr = %info.observed_addr, agent_version = %info.agent_version, "Received identify info");
+                                    self.bootstrap_health.on_identify(peer_id, &info);
+                                    self.metrics.on_identify();
+                                    write_cache(&self.writer.agent_version_cache).insert(peer_id.to_string(), info.agent_version.clone());
+                                    self.observe(Observation::Identified {
+                                        peer_id: peer_id.to_string(),
+                                        ip: Self::get_peer_ip(&info.observed_addr).ok(),
+                                        agent_version: info.agent_version.clone(),
+                                        protocol_version: info.protocol_version.clone(),
+                                    });
+                                    self.emit(ControllerEvent::PeerIdentified {
+                                        peer_id,
+                                        agent_version: info.agent_version.clone(),
+                                        protocol_version: info.protocol_version.clone(),
+                                        listen_addrs: info.listen_addrs.clone(),
+                                    });
+                                    info.listen_addrs
+                                        .into_iter()
+                                        .filter(|multi_addr| multi_addr.to_string().contains(Protocol::P2p(peer_id).tag()))
+                                        .for_each(|multi_addr| {
+                                            if let Ok(ip) = Self::get_peer_ip(&info.observed_addr) {
+                                                debug!(addr = %multi_addr, ip = %ip, "Recording newly connected peer");
+                                                self.writer.record_connected(peer_id.to_string(), ip, Self::current_timestamp());
                                             } else {
-                                                error!("[Swarm]: Failed to reconstruct peer.")
+                                                error!(addr = %info.observed_addr, "Failed to resolve peer IP");
                                             }
-                                        }
-                                    });
-                            }
-                            IdentifyEvent::Sent { peer_id } => {
-                                info!("[Identify]: Sent peer_id for identify {:?}", peer_id);
+                                            // A LAN survey does not crawl the DHT
+                                            if self.config.mode == Mode::LanSurvey {
+                                                return;
+                                            }
+                                            // Add the peer to DHT
+                                            self.swarm.behaviour_mut().add_address(&peer_id, multi_addr);
+                                            // Ask peer to discover more peers
+                                            let query_id = self.swarm.behaviour_mut().get_closest_peers(peer_id);
+                                            debug!(query_id = ?query_id, "Started closest peers lookup");
+                                            self.queries.insert(query_id, QueryContext { origin: Some(peer_id), sampled: false });
+
+                                            for i in 1..=MAX_RECONSTRUCTED_PEERS {
+                                                if let Ok(reconstructed_peer_id) = Self::generate_random_peer(peer_id, i) {
+                                                    let query_id = self.swarm.behaviour_mut().get_closest_peers(reconstructed_peer_id);
+                                                    self.queries.insert(query_id, QueryContext { origin: Some(peer_id), sampled: true });
+                                                } else {
+                                                    error!(index = i, "Failed to reconstruct peer")
+                                                }
+                                            }
+                                        });
+                                }
+                                IdentifyEvent::Sent { peer_id } => {
+                                    debug!(peer_id = %peer_id, "Sent identify info");
+                                }
+                                IdentifyEvent::Error { peer_id, error } => {
+                                    warn!(peer_id = %peer_id, error = %error, "Identify failed");
+                                }
+                                _ => {}
                             }
-                            IdentifyEvent::Error { peer_id, error } => {
-                                info!("[Identify]: Error peer_id {:?} error {:?}", peer_id, error);
+                            SwarmEvent::Behaviour(PeerNetworkEvent::Mdns(event)) => match event {
+                                MdnsEvent::Discovered(addrs_list) => {
+                                    let mut discovered: BTreeSet<PeerId> = BTreeSet::new();
+                                    for (peer, addr) in addrs_list
+                                        .into_iter()
+                                        .filter(|a| a.1.to_string().contains(Protocol::P2p(a.0).tag()))
+                                    {
+                                        info!(peer_id = %peer, addr = %addr, "Discovered peer through mdns");
+                                        // A peer announcing several addresses joins once
+                                        if discovered.insert(peer) {
+                                            self.metrics.on_mdns_discovery();
+                                            self.churn.record_join(peer.to_string(), ChurnSource::Mdns, Self::current_timestamp());
+                                            self.record_discovery(peer, DiscoverySource::Mdns, None);
+                                        }
+                                        // Peers discovered! Time to dial them
+                                        let _ = self.swarm.dial(addr);
+                                    }
+                                }
+                                MdnsEvent::Expired(addrs_list) => {
+                                    let expired: BTreeSet<PeerId> = addrs_list.into_iter().map(|a| a.0).collect();
+                                    for peer in expired {
+                                        info!(peer_id = %peer, "Mdns record expired");
+                                        self.churn.record_leave(peer.to_string(), ChurnSource::Mdns, Self::current_timestamp());
+                                    }
+                                }
                             }
-                            _ => {}
-                        }
-                        SwarmEvent::Behaviour(PeerNetworkEvent::Mdns(event)) => match event {
-                            MdnsEvent::Discovered(addrs_list) => {
-                                info!("[Mdns]: Discovered peer: {:?}", addrs_list);
-                                addrs_list
-                                    .into_iter()
-                                    .filter(|a| a.1.to_string().contains(Protocol::P2p(a.0).tag()))
-                                    .for_each(|a| {
-                                        info!(
-                                            "[Mdns]: Discovered Peer: {} {}",
-                                            a.0.to_string(),
-                                            a.1.to_string()
-                                        );
+                            SwarmEvent::Behaviour(PeerNetworkEvent::Kad(event)) => match event {
+                                KademliaEvent::OutboundQueryProgressed {
+                                    id,
+                                    result: kad::QueryResult::GetClosestPeers(Ok(ok)),
+                                    stats,
+                                    ..
+                                } => {
+                                    let context = self.queries.remove(&id);
+                                    let origin = context.as_ref().and_then(|context| context.origin);
+                                    let _span = info_span!("query", query_id = ?id, origin = origin.map(display)).entered();
+                                    info!(peers = ok.peers.len(), duration = ?stats.duration(), "Closest peers lookup finished");
+                                    debug!(peers = ?ok.peers, "Closest peers");
+                                    self.metrics.on_query("get_closest_peers", "ok");
+                                    // The lookup of a peer's own id shows what its FIND_NODE answers yield
+                                    // and which neighbours it reported. Peers close to a random key are not
+                                    // neighbours of the peer the lookup was started for.
+                                    if let Some(QueryContext { origin: Some(origin), sampled: false }) = &context {
+                                        self.bootstrap_health.on_lookup(*origin, ok.peers.len(), stats.duration());
+                                        for peer in ok.peers.iter() {
+                                            self.topology.add_edge(origin.to_string(), peer.to_string());
+                                        }
+                                    }
+                                    // Lookups of random keys sample the key space density, their peers were
+                                    // not reported by the origin
+                                    let reported_by = context
+                                        .as_ref()
+                                        .filter(|context| !context.sampled)
+                                        .and_then(|context| context.origin);
+                                    if context.as_ref().is_some_and(|context| context.sampled) {
+                                        self.estimator.add_sample(&ok.key, &ok.peers);
+                                    }
+                                    self.emit(ControllerEvent::QueryFinished {
+                                        origin,
+                                        peers: ok.peers.clone(),
+                                        duration: stats.duration(),
+                                        timed_out: false,
+                                    });
+                                    for peer in ok.peers {
+                                        self.record_discovery(peer, DiscoverySource::Kademlia, reported_by);
                                         // Peers discovered! Time to dial them
-                                        let _ = self.swarm.dial(a.1.clone());
+                                        let _ = self.swarm.dial(peer);
+                                    }
+                                }
+                                KademliaEvent::OutboundQueryProgressed {
+                                    id,
+                                    result:
+                                        kad::QueryResult::GetClosestPeers(Err(kad::GetClosestPeersError::Timeout {
+                                            peers,
+                                            ..
+                                        })),
+                                    stats,
+                                    ..
+                                } => {
+                                    let context = self.queries.remove(&id);
+                                    let origin = context.as_ref().and_then(|context| context.origin);
+                                    let _span = info_span!("query", query_id = ?id, origin = origin.map(display)).entered();
+                                    info!(peers = peers.len(), duration = ?stats.duration(), "Closest peers lookup timed out");
+                                    self.metrics.on_query("get_closest_peers", "timeout");
+                                    // Peers found before the timeout are still valid neighbours
+                                    if let Some(QueryContext { origin: Some(origin), sampled: false }) = &context {
+                                        for peer in peers.iter() {
+                                            self.topology.add_edge(origin.to_string(), peer.to_string());
+                                        }
+                                    }
+                                    self.emit(ControllerEvent::QueryFinished {
+                                        origin,
+                                        peers,
+                                        duration: stats.duration(),
+                                        timed_out: true,
                                     });
+                                }
+                                KademliaEvent::OutboundQueryProgressed {
+                                    result: kad::QueryResult::Bootstrap(result),
+                                    step,
+                                    ..
+                                } if step.last => {
+                                    // A bootstrap reports progress for every bucket it refreshes, count it once
+                                    self.metrics.on_query("bootstrap", if result.is_ok() { "ok" } else { "timeout" });
+                                }
+                                KademliaEvent::RoutingUpdated { .. } => {
+                                    self.metrics.set_bucket_sizes(self.swarm.behaviour_mut().bucket_sizes());
+                                }
+                                _ => {}
                             }
-                            MdnsEvent::Expired(addrs_list) => {
-                                info!("[Mdns]: Expired list {:?}", addrs_list);
+                            SwarmEvent::Behaviour(PeerNetworkEvent::Ping(PingEvent { peer, result, .. })) => match result {
+                                Ok(rtt) => debug!(peer_id = %peer, rtt = ?rtt, "Ping succeeded"),
+                                Err(e) => info!(peer_id = %peer, error = %e, "Ping failed"),
                             }
-                        }
-                        SwarmEvent::Behaviour(PeerNetworkEvent::Kad(event)) => match event {
-                            KademliaEvent::OutboundQueryProgressed {
-                                result: kad::QueryResult::GetClosestPeers(Ok(ok)),
-                                ..
-                            } => {
-                                if ok.peers.is_empty() {
-                                    info!("[Kad]: Query finished with no closest peers.")
+                            SwarmEvent::ConnectionEstablished { peer_id, connection_id, endpoint, num_established, .. } => {
+                                let _span = info_span!("peer", peer_id = %peer_id).entered();
+                                info!(addr = %endpoint.get_remote_address(), inbound = endpoint.is_listener(), "Connection established");
+                                self.bootstrap_health.on_connected(connection_id, peer_id);
+                                self.metrics.set_connected_peers(self.swarm.connected_peers().count());
+                                // Peers not discovered through mdns or a lookup reached us some other way
+                                let source = if endpoint.is_listener() {
+                                    DiscoverySource::Inbound
+                                } else if self.is_bootstrap_addr(endpoint.get_remote_address()) {
+                                    DiscoverySource::Bootstrap
                                 } else {
-                                    info!("[Kad]: Query finished with closest peers: {:#?}", ok.peers);
+                                    DiscoverySource::Dialed
+                                };
+                                self.record_discovery(peer_id, source, None);
+                                self.writer.record_seen(&peer_id.to_string(), Self::current_timestamp());
+                                self.observe(Observation::Connected {
+                                    peer_id: peer_id.to_string(),
+                                    address: endpoint.get_remote_address().to_string(),
+                                });
+                                self.emit(ControllerEvent::ConnectionEstablished {
+                                    peer_id,
+                                    address: endpoint.get_remote_address().clone(),
+                                });
+                                // Only the first connection to a peer starts a session
+                                if num_established.get() == 1 {
+                                    self.churn.record_join(peer_id.to_string(), ChurnSource::Connection, Self::current_timestamp());
                                 }
-                                for peer in ok.peers {
-                                    // Peers discovered! Time to dial them
-                                    let _ = self.swarm.dial(peer);
+                            }
+                            SwarmEvent::ConnectionClosed { peer_id, num_established, cause, .. } => {
+                                let _span = info_span!("peer", peer_id = %peer_id).entered();
+                                info!(error = cause.as_ref().map(display), "Connection closed");
+                                self.writer.record_seen(&peer_id.to_string(), Self::current_timestamp());
+                                self.metrics.set_connected_peers(self.swarm.connected_peers().count());
+                                self.emit(ControllerEvent::ConnectionClosed {
+                                    peer_id,
+                                    cause: cause.map(|cause| cause.to_string()),
+                                });
+                                // The session ends when the last connection to a peer is closed
+                                if num_established == 0 {
+                                    self.churn.record_leave(peer_id.to_string(), ChurnSource::Connection, Self::current_timestamp());
                                 }
                             }
-                            KademliaEvent::OutboundQueryProgressed {
-                                result:
-                                    kad::QueryResult::GetClosestPeers(Err(kad::GetClosestPeersError::Timeout {
-                                        ..
-                                    })),
-                                ..
-                            } => {
-                                info!("[Kad]: Query for closest peers timed out")
+                            SwarmEvent::IncomingConnection { local_addr, send_back_addr, .. } => {
+                                debug!(local_addr = %local_addr, send_back_addr = %send_back_addr, "Incoming connection");
                             }
-                            _ => {}
-                        }
-                        SwarmEvent::ConnectionEstablished { peer_id, .. } => {
-                            info!("[Swarm]: Connection Established {}", peer_id);
-                        }
-                        SwarmEvent::ConnectionClosed { peer_id, .. } => {
-                            info!("[Swarm]: Connection Closed  {}", peer_id);
-                        }
-                        SwarmEvent::IncomingConnection { local_addr, .. } => {
-                            info!("[Swarm]: IncomingConnection {}", local_addr);
-                        }
-                        SwarmEvent::OutgoingConnectionError { peer_id, error, ..} => {
-                            info!("[Swarm]: OutgoingConnectionError {:?}, {:?}", peer_id, error);
-                            match error {
-                                DialError::Transport(addrs) => {
-                                    for addr in addrs.iter() {
-                                        info!("[Task 1]: Log MultiAddress if not reachable {}.", addr.0);
-                                        self.writer.unreachable_peer_cache.write().unwrap().entry(addr.0.to_string())
-                                            .or_insert((
+                            SwarmEvent::OutgoingConnectionError { peer_id, connection_id, error } => {
+                                let _span = info_span!("peer", peer_id = peer_id.map(display)).entered();
+                                info!(error = %error, "Outgoing connection failed");
+                                self.bootstrap_health.on_dial_error(connection_id, error.to_string());
+                                self.metrics.on_dial_failure(&error);
+                                *self.dial_failures.entry(dial_error_class(&error)).or_default() += 1;
+                                match error {
+                                    DialError::Transport(addrs) => {
+                                        for addr in addrs.iter() {
+                                            info!(addr = %addr.0, error = %addr.1, "Address unreachable");
+                                            self.writer.record_unreachable(
+                                                addr.0.to_string(),
                                                 Status::Unreachable.to_string(),
-                                                SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis().to_string()
-                                            ));
+                                                Self::current_timestamp(),
+                                            );
+                                            self.observe(Observation::Unreachable {
+                                                peer_id: peer_id.map(|peer_id| peer_id.to_string()),
+                                                address: addr.0.to_string(),
+                                                error: addr.1.to_string(),
+                                            });
+                                            self.emit(ControllerEvent::PeerUnreachable {
+                                                address: addr.0.clone(),
+                                                error: addr.1.to_string(),
+                                            });
+                                        }
                                     }
+                                    _ => {}
                                 }
-                                _ => {}
                             }
+                            SwarmEvent::Dialing { peer_id, .. } => {
+                                debug!(peer_id = peer_id.map(display), "Dialing");
+                                self.metrics.on_dial();
+                            }
+                            e => debug!(event = ?e, "Unhandled swarm event"),
+                        }
+                    }
+                }
+                Some(command) = self.commands.next() => match command {
+                    ControllerCommand::Dial(address) => {
+                        info!(addr = %address, "Dial requested");
+                        if let Err(e) = self.swarm.dial(address.clone()) {
+                            error!(addr = %address, error = %e, "Failed to dial");
+                            self.emit(ControllerEvent::PeerUnreachable { address, error: e.to_string() });
                         }
-                        e => info!("[Swarm]: Event {:?}", e),
                     }
+                    ControllerCommand::AddBootstrap(address) => {
+                        info!(addr = %address, "Adding bootstrap node");
+                        self.config.bootstrap_addr.push(address.to_string());
+                        self.dial_bootstrap(address);
+                    }
+                    ControllerCommand::Crawl if self.config.mode == Mode::LanSurvey => {
+                        warn!("A LAN survey does not crawl the DHT, ignoring crawl request");
+                    }
+                    ControllerCommand::Crawl => self.crawl(),
+                    ControllerCommand::Snapshot(reply) => {
+                        let analysis = NetworkAnalysis::from_topology(&self.topology);
+                        let _ = reply.send(self.network_report(&analysis));
+                    }
+                    ControllerCommand::State(reply) => {
+                        let _ = reply.send(self.state(current_instant));
+                    }
+                    ControllerCommand::Flush(reply) => {
+                        info!("Flush requested, writing results");
+                        let _ = reply.send(self.write_results(current_instant));
+                    }
+                    ControllerCommand::SetLogLevel(level) => {
+                        info!(level = %level, "Setting log level");
+                        if let Err(e) = logging::set_level(level) {
+                            error!(error = %e, "Failed to set log level");
+                        }
+                    }
+                    ControllerCommand::Stop => {
+                        info!("Stop requested, writing results");
+                        stopped = true;
+                    }
+                },
+                _ = bootstrap_interval.tick(), if self.config.mode != Mode::LanSurvey => {
+                    self.crawl();
+                }
+                _ = &mut deadline, if !deadline_reached => {
+                    deadline_reached = true;
                 }
-                _ = bootstrap_interval.tick() => self.swarm.behaviour_mut().bootstrap(),
             }
 
+            // In size estimation mode stop as soon as enough lookups have been sampled
+            let estimation_complete = self.config.mode == Mode::EstimateNetworkSize
+                && self.estimator.samples.len() >= self.config.size_estimation_samples;
+
             // If time elapsed crosses max allowed operation time, write to CSV and break
-            if current_instant.elapsed() > Duration::from_secs(self.config.operation_duration) {
-                info!("[CSVWriter]: Writing newly connected peers to CSV.");
-                let _ = self
-                    .writer
-                    .append_data_to_csv(
-                        PathBuf::from_str(&self.config.just_connected).unwrap(),
-                        Status::NewlyConnected,
-                    )
-                    .await;
-
-                info!("[CSVWriter]: Writing unreachable peers to CSV");
-                let _ = self
-                    .writer
-                    .append_data_to_csv(
-                        PathBuf::from_str(&self.config.unreachable_csv).unwrap(),
-                        Status::Unreachable,
-                    )
-                    .await;
+            if stopped
+                || estimation_complete
+                || deadline_reached
+                || current_instant.elapsed() > Duration::from_secs(self.config.operation_duration)
+            {
+                self.summary = Some(self.run_summary(current_instant));
+                self.write_results(current_instant)?;
 
                 break;
             }
@@ -252,25 +587,287 @@ impl Controller {
         Ok(())
     }
 
+    /// Dial a bootstrap node and keep track of the dial, so its outcome
+    /// can be reported per bootstrap node.
+    fn dial_bootstrap(&mut self, bootstrap_node_addr: Multiaddr) {
+        let bootstrap_addr = bootstrap_node_addr.to_string();
+        let peer_id = Self::get_peer_id(&bootstrap_node_addr);
+        if let Some(peer_id) = peer_id {
+            self.record_discovery(peer_id, DiscoverySource::Bootstrap, None);
+        }
+
+        let opts = DialOpts::unknown_peer_id()
+            .address(bootstrap_node_addr)
+            .build();
+        let connection_id = opts.connection_id();
+        match self.swarm.dial(opts) {
+            Ok(()) => {
+                self.bootstrap_health
+                    .on_dial(bootstrap_addr, peer_id, Some(connection_id), None)
+            }
+            Err(e) => {
+                error!(addr = %bootstrap_addr, error = %e, "Failed to dial bootstrap node");
+                self.bootstrap_health
+                    .on_dial(bootstrap_addr, peer_id, None, Some(e.to_string()));
+            }
+        }
+    }
+
+    /// Run one crawl round: a DHT bootstrap, plus random lookups to sample
+    /// when estimating the network size.
+    fn crawl(&mut self) {
+        self.swarm.behaviour_mut().bootstrap();
+
+        if self.config.mode == Mode::EstimateNetworkSize {
+            info!(
+                lookups = SIZE_ESTIMATION_LOOKUPS,
+                "Sampling random lookups for network size"
+            );
+            for _ in 0..SIZE_ESTIMATION_LOOKUPS {
+                let query_id = self
+                    .swarm
+                    .behaviour_mut()
+                    .get_closest_peers(PeerId::random());
+                self.queries.insert(
+                    query_id,
+                    QueryContext {
+                        origin: None,
+                        sampled: true,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Collect every report section as it stands.
+    fn network_report(&self, analysis: &NetworkAnalysis) -> NetworkReport {
+        let mut report = NetworkReport::new();
+        report.push(self.bootstrap_health.to_report_section());
+        report.push(analysis.to_report_section());
+        report.push(self.estimator.to_report_section(analysis.peer_count));
+        report.push(self.churn.to_report_section(Self::current_timestamp()));
+        report.push(self.writer.discovery_report_section());
+
+        report
+    }
+
+    /// Collect the peer caches, routing table and run status as they stand.
+    fn state(&mut self, started: Instant) -> ControllerState {
+        let format = self.config.timestamp_format;
+        let discovered = read_cache(&self.writer.discovered_peer_cache);
+        let agent_versions = read_cache(&self.writer.agent_version_cache);
+        let peers: Vec<PeerState> = read_cache(&self.writer.newly_connected_peer_cache)
+            .iter()
+            .map(|(peer, (ip, seen))| {
+                let discovery = discovered.get(peer);
+                PeerState {
+                    peer_id: peer.clone(),
+                    ip: ip.clone(),
+                    first_seen: format.format(&seen.first),
+                    last_seen: format.format(&seen.last),
+                    discovery_source: discovery.map(|discovery| discovery.source.to_string()),
+                    discovered_at: discovery.map(|discovery| format.format(&discovery.timestamp)),
+                    discovered_by: discovery.and_then(|discovery| discovery.discovered_by.clone()),
+                    agent_version: agent_versions.get(peer).cloned(),
+                }
+            })
+            .collect();
+        let unreachable: Vec<UnreachableState> = read_cache(&self.writer.unreachable_peer_cache)
+            .iter()
+            .map(|(address, (status, seen))| UnreachableState {
+                address: address.clone(),
+                status: status.clone(),
+                first_seen: format.format(&seen.first),
+                last_seen: format.format(&seen.last),
+            })
+            .collect();
+        let discovered_peers = discovered.len();
+        drop(discovered);
+        drop(agent_versions);
+
+        let routing_table = self
+            .swarm
+            .behaviour_mut()
+            .routing_table()
+            .into_iter()
+            .map(|(peer_id, addresses)| RoutingEntry {
+                peer_id: peer_id.to_string(),
+                addresses: addresses.iter().map(|addr| addr.to_string()).collect(),
+            })
+            .collect();
+        let uptime = started.elapsed().as_secs();
+
+        ControllerState {
+            status: RunStatus {
+                local_peer_id: self.swarm.local_peer_id().to_string(),
+                mode: format!("{:?}", self.config.mode),
+                uptime,
+                remaining: self.config.operation_duration.saturating_sub(uptime),
+                connected_peers: self.swarm.connected_peers().count(),
+                discovered_peers,
+                unreachable_addrs: unreachable.len(),
+                pending_queries: self.queries.len(),
+            },
+            peers,
+            unreachable,
+            routing_table,
+        }
+    }
+
+    /// Summarise the run as it stands, most common agent versions first.
+    fn run_summary(&self, started: Instant) -> RunSummary {
+        let mut agent_versions = BTreeMap::new();
+        for agent_version in read_cache(&self.writer.agent_version_cache).values() {
+            *agent_versions.entry(agent_version.clone()).or_default() += 1;
+        }
+        let mut agent_versions: Vec<(String, usize)> = agent_versions.into_iter().collect();
+        agent_versions.sort_by_key(|(_, peers)| Reverse(*peers));
+        agent_versions.truncate(TOP_AGENT_VERSIONS);
+
+        RunSummary {
+            elapsed: started.elapsed(),
+            discovered_peers: read_cache(&self.writer.discovered_peer_cache).len(),
+            identified_peers: read_cache(&self.writer.newly_connected_peer_cache).len(),
+            connected_peers: self.swarm.connected_peers().count(),
+            unreachable_addrs: read_cache(&self.writer.unreachable_peer_cache).len(),
+            dial_failures: self
+                .dial_failures
+                .iter()
+                .map(|(error, count)| (error.to_string(), *count))
+                .collect(),
+            agent_versions,
+            bootstrap_nodes: self.bootstrap_health.nodes.len(),
+            bootstrap_connected: self
+                .bootstrap_health
+                .nodes
+                .iter()
+                .filter(|node| node.is_connected())
+                .count(),
+            islands: NetworkAnalysis::from_topology(&self.topology)
+                .components
+                .len(),
+        }
+    }
+
+    /// Send `event` to every event stream that is still alive.
+    fn emit(&mut self, event: ControllerEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.unbounded_send(event.clone()).is_ok());
+    }
+
+    /// Keep `observation` in the history database, if there is one.
+    fn observe(&mut self, observation: Observation) {
+        if let Some(history) = self.history.as_mut() {
+            history.observe(observation, Self::current_timestamp().timestamp_millis());
+        }
+    }
+
+    /// Record how `peer` was discovered, announcing it on the first discovery.
+    fn record_discovery(
+        &mut self,
+        peer: PeerId,
+        source: DiscoverySource,
+        discovered_by: Option<PeerId>,
+    ) {
+        let first = self.writer.record_discovery(
+            peer.to_string(),
+            source,
+            discovered_by.map(|by| by.to_string()),
+            Self::current_timestamp(),
+        );
+        if first {
+            self.emit(ControllerEvent::PeerDiscovered {
+                peer_id: peer,
+                source,
+                discovered_by,
+            });
+        }
+    }
+
+    /// Write every result file and sink once `config.operation_duration` has been covered.
+    /// All files and sinks are attempted, the first failure is returned.
+    fn write_results(&mut self, started: Instant) -> Result<(), CLIError> {
+        let mut results = Vec::new();
+
+        info!(dot = %self.config.topology_dot, graphml = %self.config.topology_graphml, "Writing network topology");
+        results.push(
+            self.topology
+                .write_to_file(PathBuf::from(&self.config.topology_dot), GraphFormat::Dot),
+        );
+        results.push(self.topology.write_to_file(
+            PathBuf::from(&self.config.topology_graphml),
+            GraphFormat::GraphML,
+        ));
+
+        info!(path = %self.config.churn_csv, "Writing churn events");
+        results.push(self.churn.write_to_csv(
+            PathBuf::from(&self.config.churn_csv),
+            self.config.timestamp_format,
+        ));
+
+        info!(path = %self.config.network_report, "Writing network report");
+        let analysis = NetworkAnalysis::from_topology(&self.topology);
+        if analysis.is_partitioned() {
+            warn!(
+                components = analysis.components.len(),
+                "Network is partitioned"
+            );
+        }
+        let connected_bootstrap_nodes = self
+            .bootstrap_health
+            .nodes
+            .iter()
+            .filter(|node| node.is_connected())
+            .count();
+        if connected_bootstrap_nodes == 0 && !self.bootstrap_health.nodes.is_empty() {
+            warn!("None of the bootstrap nodes could be reached");
+        }
+        let report = self.network_report(&analysis);
+        results.push(report.write_to_file(PathBuf::from(&self.config.network_report)));
+
+        let state = self.state(started);
+        for sink in self.sinks.iter_mut() {
+            info!(sink = %sink.name(), "Writing peers to sink");
+            results.push(sink.write(&state, &report));
+        }
+
+        if let Some(history) = self.history.as_mut() {
+            info!(run_id = history.run_id, "Writing observations to history");
+            results.push(history.write(Self::current_timestamp().timestamp_millis()));
+        }
+
+        if let Some(metrics_file) = &self.config.metrics_file {
+            info!(path = %metrics_file, "Writing metrics");
+            results.push(self.metrics.write_to_file(PathBuf::from(metrics_file)));
+        }
+
+        for e in results.iter().filter_map(|result| result.as_ref().err()) {
+            error!(error = %e, "Failed to write results");
+        }
+
+        results.into_iter().collect()
+    }
+
     /// The transport layer builder for swarm. Currently supports only tcp and quic.
-    fn build_transport_layer(config: &Config) -> std::io::Result<Boxed<(PeerId, StreamMuxerBox)>> {
+    pub(crate) fn build_transport_layer(
+        config: &Config,
+    ) -> Result<Boxed<(PeerId, StreamMuxerBox)>, CLIError> {
+        let noise_config = noise::Config::new(&config.keypair)
+            .map_err(|e| CLIError::transport("cannot sign the libp2p-noise static keypair", e))?;
         let tcp_transport = libp2p::tcp::tokio::Transport::new(TcpConfig::new().nodelay(true))
             .upgrade(upgrade::Version::V1Lazy)
-            .authenticate(
-                noise::Config::new(&config.keypair).expect("signing libp2p-noise static keypair"),
-            )
+            .authenticate(noise_config)
             .multiplex(yamux::Config::default())
             .timeout(std::time::Duration::from_secs(config.timeout))
             .boxed();
 
         let quic_transport = quic::tokio::Transport::new(quic::Config::new(&config.keypair));
 
-        info!("[Task 3]: Create Dns config to Resolve IP.");
         let transport = TokioDnsConfig::system(libp2p::core::transport::OrTransport::new(
             quic_transport,
             tcp_transport,
         ))
-        .unwrap()
+        .map_err(CLIError::dns_config)?
         .map(|either_output, _| match either_output {
             Either::Left((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
             Either::Right((peer_id, muxer)) => (peer_id, StreamMuxerBox::new(muxer)),
@@ -280,6 +877,36 @@ impl Controller {
         Ok(transport)
     }
 
+    /// A utility helper to check whether `address` is one of the configured bootstrap
+    /// addresses, ignoring any `/p2p/` suffix.
+    fn is_bootstrap_addr(&self, address: &Multiaddr) -> bool {
+        let strip_peer_id = |addr: &Multiaddr| -> Multiaddr {
+            addr.iter()
+                .filter(|protocol| !matches!(protocol, Protocol::P2p(_)))
+                .collect()
+        };
+        let address = strip_peer_id(address);
+
+        self.config
+            .bootstrap_addr
+            .iter()
+            .filter_map(|bootstrap_addr| bootstrap_addr.parse::<Multiaddr>().ok())
+            .any(|bootstrap_addr| strip_peer_id(&bootstrap_addr) == address)
+    }
+
+    /// A utility helper to get the PeerId from the `/p2p/` component of a multiaddress.
+    fn get_peer_id(multi_addr: &Multiaddr) -> Option<PeerId> {
+        multi_addr.iter().find_map(|protocol| match protocol {
+            Protocol::P2p(peer_id) => Some(peer_id),
+            _ => None,
+        })
+    }
+
+    /// A utility helper to get the current time. Every timestamp of a run is in UTC.
+    fn current_timestamp() -> DateTime<Utc> {
+        Utc::now()
+    }
+
     /// A utility helper to resolve Peer IP address via `multiaddr::Protocol`.
     fn get_peer_ip(multi_addr: &Multiaddr) -> Result<String, CLIError> {
         if let Some(protocol) = multi_addr.into_iter().next() {
@@ -287,43 +914,59 @@ impl Controller {
                 Protocol::Ip4(ip) => return Ok(ip.to_string()),
                 Protocol::Ip6(ip) => return Ok(ip.to_string()),
                 Protocol::Dns(addr) => return Ok(addr.to_string()),
-                _ => {
-                    return Err(CLIError::IPResolutionError);
-                }
+                _ => {}
             }
         }
 
-        Err(CLIError::IPResolutionError)
+        Err(CLIError::IPResolutionError {
+            context: multi_addr.to_string(),
+        })
     }
 
     /// A utility helper to generate a peer ID close to an existing with
-    /// target least common prefix length.
-    fn generate_random_peer(peer: PeerId, target_cpl: u32) -> Result<PeerId, CLIError> {
+    /// target least common prefix length. The first `target_cpl` bits of its digest
+    /// are taken from the bytes of `peer`, the rest is random.
+    pub fn generate_random_peer(peer: PeerId, target_cpl: u32) -> Result<PeerId, CLIError> {
         let origin_peer_id = peer.to_bytes();
         let mut peer_id = rand::thread_rng().gen::<[u8; 32]>();
 
-        let mask = (1 << target_cpl) - 1;
-        for i in 0..4 {
-            peer_id[i] = (peer_id[i] & !mask) | (origin_peer_id[i] & mask);
+        for (i, (byte, origin)) in peer_id.iter_mut().zip(origin_peer_id).enumerate() {
+            // Bits of the prefix that fall into this byte, most significant first
+            let bits = target_cpl.saturating_sub(8 * i as u32).min(8);
+            let mask = 0xFFu8.checked_shl(8 - bits).unwrap_or(0);
+            *byte = (*byte & !mask) | (origin & mask);
         }
 
-        let multihash = Multihash::wrap(0x0, &peer_id).map_err(|_| CLIError::IdentityError)?;
-
-        PeerId::from_multihash(multihash).map_err(|_| CLIError::IdentityError)
+        let multihash = Multihash::wrap(0x0, &peer_id)
+            .map_err(|e| CLIError::peer_id("cannot wrap reconstructed peer id", e))?;
+
+        PeerId::from_multihash(multihash).map_err(|multihash| {
+            CLIError::peer_id(
+                "reconstructed peer id is not a valid multihash",
+                format!(
+                    "unsupported multihash code {:#x} with a {} byte digest",
+                    multihash.code(),
+                    multihash.digest().len()
+                ),
+            )
+        })
     }
 }
 
 impl Drop for Controller {
     fn drop(&mut self) {
-        let cache = self.writer.newly_connected_peer_cache.read().unwrap();
+        let cache = read_cache(&self.writer.newly_connected_peer_cache);
 
-        info!("Cleaning up network artifacts....");
+        info!("Cleaning up network artifacts");
         for (peer, _) in cache.iter() {
-            self.swarm
-                .behaviour_mut()
-                .remove_peer(PeerId::from_str(peer).unwrap());
+            match PeerId::from_str(peer) {
+                Ok(peer_id) => self.swarm.behaviour_mut().remove_peer(peer_id),
+                Err(e) => warn!(peer_id = %peer, error = %e, "Cannot remove peer"),
+            }
         }
     }
 }
 
 const MAX_RECONSTRUCTED_PEERS: u32 = 15;
+const SIZE_ESTIMATION_LOOKUPS: usize = 4;
+const TOP_AGENT_VERSIONS: usize = 5;
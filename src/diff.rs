@@ -0,0 +1,236 @@
+//! Compare two reports of this tool to show what changed between runs.
+
+use csv::{Reader, StringRecord};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    path::Path,
+};
+
+use crate::{
+    error::CLIError,
+    report::{NetworkReport, ReportSection},
+    state::{PeerState, UnreachableState},
+    writer::Headers,
+};
+
+/// Peers and unreachable addresses of one run, as read back from its reports.
+#[derive(Clone, Debug, Default)]
+pub struct PeerSnapshot {
+    /// Stores (PeerID -> (IP, Agent version))
+    pub peers: BTreeMap<String, (String, Option<String>)>,
+    /// Stores (MultiAddress)
+    pub unreachable: BTreeSet<String>,
+}
+
+/// The parts of a JSON report a diff needs.
+#[derive(Deserialize)]
+struct JsonReport {
+    peers: Vec<PeerState>,
+    unreachable: Vec<UnreachableState>,
+}
+
+impl PeerSnapshot {
+    /// Read every report in `paths` into one snapshot. JSON reports are recognised
+    /// by their `.json` extension, CSV reports by their header row.
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self, CLIError> {
+        let mut snapshot = PeerSnapshot::default();
+
+        for path in paths.iter().map(AsRef::as_ref) {
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                snapshot.read_json(path)?;
+            } else {
+                snapshot.read_csv(path)?;
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    fn read_json(&mut self, path: &Path) -> Result<(), CLIError> {
+        let file = File::open(path).map_err(|e| CLIError::report_read(path, e))?;
+        let report: JsonReport =
+            serde_json::from_reader(file).map_err(|e| CLIError::report_read(path, e))?;
+
+        for peer in report.peers {
+            self.peers
+                .insert(peer.peer_id, (peer.ip, peer.agent_version));
+        }
+        self.unreachable
+            .extend(report.unreachable.into_iter().map(|peer| peer.address));
+
+        Ok(())
+    }
+
+    fn read_csv(&mut self, path: &Path) -> Result<(), CLIError> {
+        let mut reader = Reader::from_path(path).map_err(|e| CLIError::report_read(path, e))?;
+        let headers = reader
+            .headers()
+            .map_err(|e| CLIError::report_read(path, e))?
+            .clone();
+        let column = |header: Headers| {
+            let header = header.to_string();
+            headers.iter().position(|column| column == header)
+        };
+        let (ip, status, agent_version) = (
+            column(Headers::IpAddr),
+            column(Headers::Status),
+            column(Headers::AgentVersion),
+        );
+        if ip.is_none() && status.is_none() {
+            return Err(CLIError::report_read(
+                path,
+                "expected a newly connected or unreachable peers CSV",
+            ));
+        }
+
+        for record in reader.records() {
+            let record: StringRecord = record.map_err(|e| CLIError::report_read(path, e))?;
+            let field = |index: Option<usize>| {
+                index
+                    .and_then(|index| record.get(index))
+                    .filter(|value| !value.is_empty())
+                    .map(String::from)
+            };
+            let Some(peer) = field(Some(0)) else {
+                continue;
+            };
+
+            if ip.is_some() {
+                self.peers
+                    .insert(peer, (field(ip).unwrap_or_default(), field(agent_version)));
+            } else {
+                self.unreachable.insert(peer);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What changed from one run to another.
+#[derive(Clone, Debug, Default)]
+pub struct ReportDiff {
+    /// (PeerID, IP) of peers connected after but not before
+    pub added: Vec<(String, String)>,
+    /// (PeerID, IP) of peers connected before but not after
+    pub removed: Vec<(String, String)>,
+    /// Addresses unreachable after but not before
+    pub newly_unreachable: Vec<String>,
+    /// Addresses unreachable before but not after
+    pub recovered: Vec<String>,
+    /// (PeerID, IP before, IP after)
+    pub ip_changed: Vec<(String, String, String)>,
+    /// (PeerID, Agent version before, Agent version after)
+    pub version_changed: Vec<(String, String, String)>,
+}
+
+impl ReportDiff {
+    pub fn between(before: &PeerSnapshot, after: &PeerSnapshot) -> Self {
+        let mut diff = ReportDiff::default();
+
+        for (peer, (ip, agent_version)) in after.peers.iter() {
+            let Some((ip_before, agent_version_before)) = before.peers.get(peer) else {
+                diff.added.push((peer.clone(), ip.clone()));
+                continue;
+            };
+            if ip_before != ip {
+                diff.ip_changed
+                    .push((peer.clone(), ip_before.clone(), ip.clone()));
+            }
+            // A report without agent versions tells nothing about version changes
+            if let (Some(version_before), Some(version)) = (agent_version_before, agent_version) {
+                if version_before != version {
+                    diff.version_changed.push((
+                        peer.clone(),
+                        version_before.clone(),
+                        version.clone(),
+                    ));
+                }
+            }
+        }
+        diff.removed = before
+            .peers
+            .iter()
+            .filter(|(peer, _)| !after.peers.contains_key(*peer))
+            .map(|(peer, (ip, _))| (peer.clone(), ip.clone()))
+            .collect();
+        diff.newly_unreachable = after
+            .unreachable
+            .difference(&before.unreachable)
+            .cloned()
+            .collect();
+        diff.recovered = before
+            .unreachable
+            .difference(&after.unreachable)
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    /// A summary section followed by a section for every non-empty change.
+    pub fn to_report(&self) -> NetworkReport {
+        let mut report = NetworkReport::new();
+
+        let mut summary = ReportSection::new("Changes");
+        summary.push("Peers added", self.added.len());
+        summary.push("Peers removed", self.removed.len());
+        summary.push("Newly unreachable", self.newly_unreachable.len());
+        summary.push("Recovered", self.recovered.len());
+        summary.push("IP changed", self.ip_changed.len());
+        summary.push("Version changed", self.version_changed.len());
+        report.push(summary);
+
+        let peers = [
+            ("Peers Added", &self.added),
+            ("Peers Removed", &self.removed),
+        ];
+        for (title, entries) in peers {
+            if entries.is_empty() {
+                continue;
+            }
+            let mut section = ReportSection::new(title);
+            for (peer, ip) in entries.iter() {
+                section.push(peer, ip);
+            }
+            report.push(section);
+        }
+
+        let addresses = [
+            ("Newly Unreachable", &self.newly_unreachable, "Unreachable"),
+            ("Recovered", &self.recovered, "No longer unreachable"),
+        ];
+        for (title, entries, status) in addresses {
+            if entries.is_empty() {
+                continue;
+            }
+            let mut section = ReportSection::new(title);
+            for address in entries.iter() {
+                section.push(address, status);
+            }
+            report.push(section);
+        }
+
+        let changed = [
+            ("IP Changed", &self.ip_changed),
+            ("Version Changed", &self.version_changed),
+        ];
+        for (title, entries) in changed {
+            if entries.is_empty() {
+                continue;
+            }
+            let mut section = ReportSection::new(title);
+            for (peer, before, after) in entries.iter() {
+                section.push(peer, format!("{} -> {}", before, after));
+            }
+            report.push(section);
+        }
+
+        report
+    }
+}
@@ -1,27 +1,217 @@
 //! Error handler for the tool.
 
-use std::fmt;
+use std::{error::Error, fmt, path::Path};
+
+/// Boxed underlying error kept as the source of a `CLIError`.
+pub type BoxError = Box<dyn Error + Send + Sync>;
 
 #[derive(Debug)]
 pub enum CLIError {
-    IdentityError,
-    IPResolutionError,
-    ResourceBusy,
-    WriterError,
+    IdentityError {
+        context: String,
+        source: Option<BoxError>,
+    },
+    IPResolutionError {
+        context: String,
+    },
+    ResourceBusy {
+        context: String,
+        source: Option<BoxError>,
+    },
+    TransportError {
+        context: String,
+        source: Option<BoxError>,
+    },
+    DnsConfigError {
+        source: Option<BoxError>,
+    },
+    PeerIdError {
+        context: String,
+        source: Option<BoxError>,
+    },
+    WriterError {
+        path: String,
+        source: Option<BoxError>,
+    },
+    InvalidBootstrapAddr {
+        addr: String,
+        reason: String,
+    },
+    BootstrapUnreachable {
+        addr: String,
+        reason: String,
+    },
+    InvalidLogFilter {
+        directive: String,
+        reason: String,
+    },
+    BootstrapSourceError {
+        context: String,
+        source: Option<BoxError>,
+    },
+    ReportReadError {
+        path: String,
+        source: Option<BoxError>,
+    },
+}
+
+impl CLIError {
+    pub fn identity(context: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        CLIError::IdentityError {
+            context: context.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn resource_busy(context: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        CLIError::ResourceBusy {
+            context: context.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn transport(context: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        CLIError::TransportError {
+            context: context.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn dns_config(source: impl Into<BoxError>) -> Self {
+        CLIError::DnsConfigError {
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn peer_id(context: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        CLIError::PeerIdError {
+            context: context.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn writer(path: &Path, source: impl Into<BoxError>) -> Self {
+        CLIError::WriterError {
+            path: path.display().to_string(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn bootstrap_source(context: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        CLIError::BootstrapSourceError {
+            context: context.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn report_read(path: &Path, source: impl Into<BoxError>) -> Self {
+        CLIError::ReportReadError {
+            path: path.display().to_string(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Process exit code for the error, so callers can tell failure classes apart.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CLIError::IdentityError { .. }
+            | CLIError::InvalidBootstrapAddr { .. }
+            | CLIError::InvalidLogFilter { .. }
+            | CLIError::BootstrapSourceError { .. }
+            | CLIError::ReportReadError { .. } => CONFIG_EXIT_CODE,
+            CLIError::ResourceBusy { .. }
+            | CLIError::TransportError { .. }
+            | CLIError::DnsConfigError { .. }
+            | CLIError::PeerIdError { .. } => RESOURCE_EXIT_CODE,
+            CLIError::IPResolutionError { .. } => IP_RESOLUTION_EXIT_CODE,
+            CLIError::WriterError { .. } => WRITER_EXIT_CODE,
+            CLIError::BootstrapUnreachable { .. } => BOOTSTRAP_UNREACHABLE_EXIT_CODE,
+        }
+    }
 }
 
 impl fmt::Display for CLIError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            CLIError::IdentityError => write!(f, "{}", IDENTITY_ERROR),
-            CLIError::IPResolutionError => write!(f, "{}", IP_RESOLUTION_ERROR),
-            CLIError::ResourceBusy => write!(f, "{}", RESOURCE_BUSY),
-            CLIError::WriterError => write!(f, "{}", WRITER_FLUSH_ERROR),
+            CLIError::IdentityError { context, .. } => {
+                write!(f, "{} ({})", IDENTITY_ERROR, context)
+            }
+            CLIError::IPResolutionError { context } => {
+                write!(f, "{} ({})", IP_RESOLUTION_ERROR, context)
+            }
+            CLIError::ResourceBusy { context, .. } => {
+                write!(f, "{} ({})", RESOURCE_BUSY, context)
+            }
+            CLIError::TransportError { context, .. } => {
+                write!(f, "{} ({})", TRANSPORT_ERROR, context)
+            }
+            CLIError::DnsConfigError { .. } => write!(f, "{}", DNS_CONFIG_ERROR),
+            CLIError::PeerIdError { context, .. } => {
+                write!(f, "{} ({})", PEER_ID_ERROR, context)
+            }
+            CLIError::WriterError { path, .. } => {
+                write!(f, "{} ({})", WRITER_FLUSH_ERROR, path)
+            }
+            CLIError::InvalidBootstrapAddr { addr, reason } => {
+                write!(f, "Invalid bootstrap address `{}`: {}.", addr, reason)
+            }
+            CLIError::BootstrapUnreachable { addr, reason } => {
+                write!(
+                    f,
+                    "Bootstrap node `{}` could not be reached: {}.",
+                    addr, reason
+                )
+            }
+            CLIError::InvalidLogFilter { directive, reason } => {
+                write!(
+                    f,
+                    "Invalid log filter directive `{}`: {}.",
+                    directive, reason
+                )
+            }
+            CLIError::BootstrapSourceError { context, .. } => {
+                write!(f, "Could not load bootstrap addresses: {}.", context)
+            }
+            CLIError::ReportReadError { path, .. } => {
+                write!(f, "Could not read the report {}.", path)
+            }
+        }
+    }
+}
+
+impl Error for CLIError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CLIError::IdentityError { source, .. }
+            | CLIError::ResourceBusy { source, .. }
+            | CLIError::TransportError { source, .. }
+            | CLIError::DnsConfigError { source }
+            | CLIError::PeerIdError { source, .. }
+            | CLIError::WriterError { source, .. }
+            | CLIError::BootstrapSourceError { source, .. }
+            | CLIError::ReportReadError { source, .. } => source
+                .as_ref()
+                .map(|source| source.as_ref() as &(dyn Error + 'static)),
+            CLIError::IPResolutionError { .. }
+            | CLIError::InvalidBootstrapAddr { .. }
+            | CLIError::InvalidLogFilter { .. }
+            | CLIError::BootstrapUnreachable { .. } => None,
         }
     }
 }
 
 const IDENTITY_ERROR: &str = "Identity failed to parse the string to get a peer MultiAddress. Please check the input address format.";
 const IP_RESOLUTION_ERROR: &str = "Could not resolve IP from libp2p multiaddress.";
-const RESOURCE_BUSY: &str = " System resources busy. Please restart the client.";
+const RESOURCE_BUSY: &str = "System resources busy. Please restart the client.";
+const TRANSPORT_ERROR: &str =
+    "Could not set up the libp2p transport. Please check the node keypair.";
+const DNS_CONFIG_ERROR: &str =
+    "Could not read the system DNS configuration. Please check /etc/resolv.conf.";
+const PEER_ID_ERROR: &str = "Could not generate a peer ID close to a known peer.";
 const WRITER_FLUSH_ERROR: &str = "Error occured while flusing buffer to file. Please check if your path is correct or if it has write access";
+
+const CONFIG_EXIT_CODE: u8 = 2;
+const RESOURCE_EXIT_CODE: u8 = 3;
+const IP_RESOLUTION_EXIT_CODE: u8 = 4;
+const WRITER_EXIT_CODE: u8 = 5;
+const BOOTSTRAP_UNREACHABLE_EXIT_CODE: u8 = 6;
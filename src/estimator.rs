@@ -0,0 +1,124 @@
+//! Network size estimation from the distance distribution of closest peers.
+//!
+//! For a lookup of a random key in a network of `N` peers spread uniformly over
+//! the Kademlia key space, the `i`-th closest peer is expected at normalised XOR
+//! distance `i / (N + 1)`. Every finished lookup is therefore one sample of `N`.
+
+use libp2p::PeerId;
+use sha2::{Digest, Sha256};
+
+use crate::report::ReportSection;
+
+#[derive(Clone, Debug, Default)]
+pub struct NetworkSizeEstimator {
+    /// Per lookup estimates of the network size
+    pub samples: Vec<f64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NetworkSizeEstimate {
+    /// Number of lookups the estimate is based on
+    pub samples: usize,
+    /// Mean of the per lookup estimates
+    pub estimate: f64,
+    /// Lower bound of the 95% confidence interval
+    pub lower_bound: f64,
+    /// Upper bound of the 95% confidence interval
+    pub upper_bound: f64,
+}
+
+impl NetworkSizeEstimator {
+    pub fn new() -> Self {
+        NetworkSizeEstimator {
+            samples: Vec::new(),
+        }
+    }
+
+    /// Record the closest peers returned by a lookup for the key `target`.
+    pub fn add_sample(&mut self, target: &[u8], peers: &[PeerId]) {
+        let target = Sha256::digest(target);
+        let mut distances: Vec<f64> = peers
+            .iter()
+            .map(|peer| normalised_distance(&target, &Sha256::digest(peer.to_bytes())))
+            .collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+
+        // Least squares fit of `d_i = i / (N + 1)` through the origin
+        let (weighted, squares) =
+            distances
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(weighted, squares), (i, distance)| {
+                    let rank = (i + 1) as f64;
+                    (weighted + rank * distance, squares + rank * rank)
+                });
+        if weighted > 0.0 {
+            self.samples.push(squares / weighted - 1.0);
+        }
+    }
+
+    /// Mean of the per lookup estimates with a 95% confidence interval.
+    pub fn estimate(&self) -> Option<NetworkSizeEstimate> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let count = self.samples.len() as f64;
+        let mean = self.samples.iter().sum::<f64>() / count;
+        let margin = if self.samples.len() > 1 {
+            let variance = self
+                .samples
+                .iter()
+                .map(|sample| (sample - mean).powi(2))
+                .sum::<f64>()
+                / (count - 1.0);
+            Z_95 * (variance / count).sqrt()
+        } else {
+            f64::INFINITY
+        };
+
+        Some(NetworkSizeEstimate {
+            samples: self.samples.len(),
+            estimate: mean,
+            lower_bound: (mean - margin).max(0.0),
+            upper_bound: mean + margin,
+        })
+    }
+
+    /// Summarise the estimate as a report section.
+    pub fn to_report_section(&self, observed_peers: usize) -> ReportSection {
+        let mut section = ReportSection::new("Network Size Estimate");
+
+        section.push("Distinct peers observed", observed_peers);
+        match self.estimate() {
+            Some(estimate) => {
+                section.push("Lookups sampled", estimate.samples);
+                section.push("Estimated peers", format!("{:.0}", estimate.estimate));
+                section.push(
+                    "95% confidence interval",
+                    format!(
+                        "{:.0} - {:.0}",
+                        estimate.lower_bound.max(observed_peers as f64),
+                        estimate.upper_bound.max(observed_peers as f64)
+                    ),
+                );
+            }
+            None => section.push("Lookups sampled", 0),
+        }
+
+        section
+    }
+}
+
+/// XOR distance between two Kademlia keys scaled to `[0, 1)`.
+fn normalised_distance(a: &[u8], b: &[u8]) -> f64 {
+    let mut prefix = [0u8; 8];
+    for (i, byte) in prefix.iter_mut().enumerate() {
+        *byte = a[i] ^ b[i];
+    }
+
+    u64::from_be_bytes(prefix) as f64 / 2f64.powi(64)
+}
+
+/// Two sided z-score for a 95% confidence interval.
+const Z_95: f64 = 1.96;
@@ -0,0 +1,46 @@
+//! Typed events emitted by the controller for library users.
+
+use libp2p::{Multiaddr, PeerId};
+use std::time::Duration;
+
+use crate::writer::DiscoverySource;
+
+#[derive(Clone, Debug)]
+pub enum ControllerEvent {
+    /// A peer was discovered for the first time
+    PeerDiscovered {
+        peer_id: PeerId,
+        source: DiscoverySource,
+        /// Peer that reported this peer, if any
+        discovered_by: Option<PeerId>,
+    },
+    /// A peer answered an identify request
+    PeerIdentified {
+        peer_id: PeerId,
+        agent_version: String,
+        protocol_version: String,
+        listen_addrs: Vec<Multiaddr>,
+    },
+    ConnectionEstablished {
+        peer_id: PeerId,
+        address: Multiaddr,
+    },
+    ConnectionClosed {
+        peer_id: PeerId,
+        /// Reason the connection was closed, if it failed
+        cause: Option<String>,
+    },
+    /// An address could not be dialed
+    PeerUnreachable {
+        address: Multiaddr,
+        error: String,
+    },
+    /// A `get_closest_peers` query finished or timed out
+    QueryFinished {
+        /// Peer the query was issued on behalf of, if any
+        origin: Option<PeerId>,
+        peers: Vec<PeerId>,
+        duration: Option<Duration>,
+        timed_out: bool,
+    },
+}
@@ -0,0 +1,98 @@
+//! Handle to control a running controller from another task.
+
+use futures::channel::{mpsc::UnboundedSender, oneshot};
+use libp2p::Multiaddr;
+use tracing::level_filters::LevelFilter;
+
+use crate::{
+    bootstrap::validate_bootstrap_addr, error::CLIError, report::NetworkReport,
+    state::ControllerState,
+};
+
+/// Commands accepted by a running controller.
+#[derive(Debug)]
+pub(crate) enum ControllerCommand {
+    Dial(Multiaddr),
+    AddBootstrap(Multiaddr),
+    Crawl,
+    Snapshot(oneshot::Sender<NetworkReport>),
+    State(oneshot::Sender<ControllerState>),
+    Flush(oneshot::Sender<Result<(), CLIError>>),
+    SetLogLevel(LevelFilter),
+    Stop,
+}
+
+/// Cloneable handle to drive a running controller. Commands sent after the
+/// controller has stopped are ignored.
+#[derive(Clone, Debug)]
+pub struct ControllerHandle {
+    commands: UnboundedSender<ControllerCommand>,
+}
+
+impl ControllerHandle {
+    pub(crate) fn new(commands: UnboundedSender<ControllerCommand>) -> Self {
+        ControllerHandle { commands }
+    }
+
+    /// Dial `address`. Failures are reported as `ControllerEvent::PeerUnreachable`.
+    pub fn dial(&self, address: Multiaddr) {
+        self.send(ControllerCommand::Dial(address));
+    }
+
+    /// Validate `address` and dial it as an additional bootstrap node,
+    /// so it shows up in the bootstrap section of the report.
+    pub fn add_bootstrap(&self, address: &str) -> Result<(), CLIError> {
+        let address = validate_bootstrap_addr(address)?;
+        self.send(ControllerCommand::AddBootstrap(address));
+
+        Ok(())
+    }
+
+    /// Start a crawl round right away instead of waiting for `config.bootstrap_period`.
+    pub fn crawl(&self) {
+        self.send(ControllerCommand::Crawl);
+    }
+
+    /// Get the network report as it stands, without writing any files.
+    /// Returns `None` if the controller has stopped.
+    pub async fn snapshot(&self) -> Option<NetworkReport> {
+        let (reply, report) = oneshot::channel();
+        self.send(ControllerCommand::Snapshot(reply));
+
+        report.await.ok()
+    }
+
+    /// Get the peer caches, routing table and run status as they stand.
+    /// Returns `None` if the controller has stopped.
+    pub async fn state(&self) -> Option<ControllerState> {
+        let (reply, state) = oneshot::channel();
+        self.send(ControllerCommand::State(reply));
+
+        state.await.ok()
+    }
+
+    /// Write every result file without stopping the controller.
+    /// Returns `None` if the controller has stopped.
+    pub async fn flush(&self) -> Option<Result<(), CLIError>> {
+        let (reply, result) = oneshot::channel();
+        self.send(ControllerCommand::Flush(reply));
+
+        result.await.ok()
+    }
+
+    /// Change the maximum log level of every target, replacing the filter set through
+    /// `RUST_LOG`. Only applies to a logger installed through [`crate::logging::init`].
+    pub fn set_log_level(&self, level: LevelFilter) {
+        self.send(ControllerCommand::SetLogLevel(level));
+    }
+
+    /// Stop the controller. The results are still written and the event stream
+    /// ends once the controller has stopped.
+    pub fn stop(&self) {
+        self.send(ControllerCommand::Stop);
+    }
+
+    fn send(&self, command: ControllerCommand) {
+        let _ = self.commands.unbounded_send(command);
+    }
+}
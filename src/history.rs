@@ -0,0 +1,233 @@
+//! History database that keeps every run and peer observation across runs.
+
+use rusqlite::{params, Connection};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+use crate::{config::Config, error::CLIError};
+
+/// A peer observation made during a run.
+#[derive(Clone, Debug)]
+pub enum Observation {
+    /// The peer answered identify
+    Identified {
+        peer_id: String,
+        ip: Option<String>,
+        agent_version: String,
+        protocol_version: String,
+    },
+    /// A connection to the peer was established
+    Connected { peer_id: String, address: String },
+    /// An address of the peer could not be dialed
+    Unreachable {
+        peer_id: Option<String>,
+        address: String,
+        error: String,
+    },
+}
+
+impl Observation {
+    fn kind(&self) -> &'static str {
+        match self {
+            Observation::Identified { .. } => "Identified",
+            Observation::Connected { .. } => "Connected",
+            Observation::Unreachable { .. } => "Unreachable",
+        }
+    }
+}
+
+/// What the history database knows about a peer across every run.
+#[derive(Clone, Debug, Default)]
+pub struct PeerHistory {
+    /// Unix timestamp the peer was first observed at (in ms)
+    pub first_seen: Option<i64>,
+    /// Unix timestamp the peer was last observed at (in ms)
+    pub last_seen: Option<i64>,
+    /// Number of runs the peer was observed in
+    pub runs: i64,
+    /// Number of failed dials to any address of the peer
+    pub unreachable: i64,
+    /// (Agent version, Unix timestamp it was first reported at) in the order reported
+    pub agent_versions: Vec<(String, i64)>,
+}
+
+pub struct HistoryDb {
+    path: PathBuf,
+    connection: Connection,
+    /// Id of the run in the `runs` table
+    pub run_id: i64,
+    /// Observations not written yet, as (Unix timestamp in ms, Observation)
+    pending: Vec<(i64, Observation)>,
+}
+
+impl HistoryDb {
+    /// Open or create the database at `path` and record the start of a run.
+    pub fn open(path: &Path, config: &Config, started_at: i64) -> Result<Self, CLIError> {
+        let connection = Connection::open(path).map_err(|e| CLIError::writer(path, e))?;
+        connection
+            .execute_batch(HISTORY_SCHEMA)
+            .map_err(|e| CLIError::writer(path, e))?;
+
+        let settings = json!({
+            "mode": format!("{:?}", config.mode),
+            "protocol": config.protocol,
+            "listening_port": config.listening_port,
+            "bootstrap_addr": config.bootstrap_addr,
+            "bootstrap_period": config.bootstrap_period,
+            "operation_duration": config.operation_duration,
+            "mdns": format!("{:?}", config.mdns),
+        });
+        connection
+            .execute(
+                "INSERT INTO runs (started_at, local_peer_id, mode, config) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    started_at,
+                    config.keypair.public().to_peer_id().to_string(),
+                    format!("{:?}", config.mode),
+                    settings.to_string()
+                ],
+            )
+            .map_err(|e| CLIError::writer(path, e))?;
+        let run_id = connection.last_insert_rowid();
+
+        Ok(HistoryDb {
+            path: path.to_path_buf(),
+            connection,
+            run_id,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Keep `observation` until the next write.
+    pub fn observe(&mut self, observation: Observation, timestamp: i64) {
+        self.pending.push((timestamp, observation));
+    }
+
+    /// Write every pending observation and mark the run as ended at `ended_at`.
+    /// A run that is flushed several times keeps the time of its last write.
+    pub fn write(&mut self, ended_at: i64) -> Result<(), CLIError> {
+        self.write_observations(ended_at)
+            .map_err(|e| CLIError::writer(&self.path, e))?;
+        self.pending.clear();
+
+        Ok(())
+    }
+
+    fn write_observations(&mut self, ended_at: i64) -> Result<(), rusqlite::Error> {
+        let transaction = self.connection.transaction()?;
+        for (timestamp, observation) in self.pending.iter() {
+            let (peer_id, address, ip, agent_version, protocol_version, error) = match observation {
+                Observation::Identified {
+                    peer_id,
+                    ip,
+                    agent_version,
+                    protocol_version,
+                } => (
+                    Some(peer_id),
+                    None,
+                    ip.as_ref(),
+                    Some(agent_version),
+                    Some(protocol_version),
+                    None,
+                ),
+                Observation::Connected { peer_id, address } => {
+                    (Some(peer_id), Some(address), None, None, None, None)
+                }
+                Observation::Unreachable {
+                    peer_id,
+                    address,
+                    error,
+                } => (
+                    peer_id.as_ref(),
+                    Some(address),
+                    None,
+                    None,
+                    None,
+                    Some(error),
+                ),
+            };
+            transaction.execute(
+                "INSERT INTO observations \
+                 (run_id, observed_at, kind, peer_id, address, ip, agent_version, protocol_version, error) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    self.run_id,
+                    timestamp,
+                    observation.kind(),
+                    peer_id,
+                    address,
+                    ip,
+                    agent_version,
+                    protocol_version,
+                    error
+                ],
+            )?;
+        }
+        transaction.execute(
+            "UPDATE runs SET ended_at = ?1 WHERE run_id = ?2",
+            params![ended_at, self.run_id],
+        )?;
+
+        transaction.commit()
+    }
+
+    /// Everything recorded about `peer_id` across every run written so far.
+    pub fn peer_history(&self, peer_id: &str) -> Result<PeerHistory, CLIError> {
+        self.query_peer_history(peer_id)
+            .map_err(|e| CLIError::writer(&self.path, e))
+    }
+
+    fn query_peer_history(&self, peer_id: &str) -> Result<PeerHistory, rusqlite::Error> {
+        let (first_seen, last_seen, runs) = self.connection.query_row(
+            "SELECT MIN(observed_at), MAX(observed_at), COUNT(DISTINCT run_id) \
+             FROM observations WHERE peer_id = ?1 AND kind != 'Unreachable'",
+            params![peer_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let unreachable = self.connection.query_row(
+            "SELECT COUNT(*) FROM observations WHERE peer_id = ?1 AND kind = 'Unreachable'",
+            params![peer_id],
+            |row| row.get(0),
+        )?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT agent_version, MIN(observed_at) FROM observations \
+             WHERE peer_id = ?1 AND kind = 'Identified' \
+             GROUP BY agent_version ORDER BY MIN(observed_at)",
+        )?;
+        let agent_versions = statement
+            .query_map(params![peer_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PeerHistory {
+            first_seen,
+            last_seen,
+            runs,
+            unreachable,
+            agent_versions,
+        })
+    }
+}
+
+const HISTORY_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        run_id INTEGER PRIMARY KEY AUTOINCREMENT,
+        started_at INTEGER NOT NULL,
+        ended_at INTEGER,
+        local_peer_id TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        config TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS observations (
+        run_id INTEGER NOT NULL REFERENCES runs (run_id),
+        observed_at INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        peer_id TEXT,
+        address TEXT,
+        ip TEXT,
+        agent_version TEXT,
+        protocol_version TEXT,
+        error TEXT
+    );
+    CREATE INDEX IF NOT EXISTS observations_peer_id ON observations (peer_id);
+";
@@ -4,18 +4,73 @@
 //!
 //! # Example
 //! ```no_run
-//! use log::info;
+//! use tracing::info;
 //! use libp2p_test_tool::{Config, Controller};
 //!
 //! let mut config: Config = Config::default();
 //! let _ = Controller::new(config.clone()).await?.start().await;
 //! info!("Libp2p Network Interaction complete. The results are saved in {} and {}", config.just_connected, config.unreachable_csv);
 //! ```
+//!
+//! The controller can also run in the background while its events are streamed:
+//! ```no_run
+//! use futures::StreamExt;
+//! use libp2p_test_tool::{Config, Controller, ControllerEvent};
+//!
+//! let mut controller = Controller::new(Config::default()).await?;
+//! let mut events = controller.events();
+//! let handle = controller.handle();
+//! tokio::spawn(async move { controller.run().await });
+//!
+//! while let Some(event) = events.next().await {
+//!     if let ControllerEvent::PeerIdentified { peer_id, .. } = event {
+//!         info!("Identified {}", peer_id);
+//!         handle.stop();
+//!     }
+//! }
+//! ```
+//!
+//! Results can be written to several sinks at once, including custom ones:
+//! ```no_run
+//! use libp2p_test_tool::{sink::StdoutSink, Config, Controller, SinkKind};
+//!
+//! let mut config = Config::default();
+//! config.sinks = vec![SinkKind::Csv, SinkKind::Json(String::from("/home/report.json"))];
+//! let mut controller = Controller::new(config).await?;
+//! controller.add_sink(Box::new(StdoutSink));
+//! let _ = controller.start().await;
+//! ```
 
+pub mod analysis;
+mod api;
 mod behaviour;
+pub mod bootstrap;
+pub mod churn;
 pub mod config;
 pub mod controller;
+pub mod diff;
 mod error;
+pub mod estimator;
+pub mod events;
+pub mod handle;
+pub mod history;
+pub mod logging;
+pub mod metrics;
+pub mod preflight;
+pub mod report;
+pub mod sink;
+pub mod state;
+pub mod summary;
+pub mod topology;
+#[cfg(feature = "tui")]
+pub mod tui;
 pub mod writer;
 
-pub use crate::{config::Config, controller::Controller, error::CLIError};
+pub use crate::{
+    config::{Config, CsvNaming, LogFormat, MdnsMode, Mode, SinkKind, TimestampFormat},
+    controller::Controller,
+    error::CLIError,
+    events::ControllerEvent,
+    handle::ControllerHandle,
+    sink::ReportSink,
+};
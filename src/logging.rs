@@ -0,0 +1,128 @@
+//! Log output of the tool, as text or JSON lines on stdout or in a file, with a filter that can be
+//! changed while running.
+
+use std::{
+    env,
+    fs::OpenOptions,
+    io::{self, IsTerminal},
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{
+    fmt, fmt::writer::BoxMakeWriter, prelude::*, reload, EnvFilter, Registry,
+};
+
+use crate::{
+    config::{Config, LogFormat},
+    error::CLIError,
+};
+
+/// Handle to replace the filter of the installed subscriber
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Install the global subscriber, writing to `config.log_file` or stdout.
+/// Records of the `log` crate, e.g. from libp2p, are forwarded to the subscriber.
+pub fn init(config: &Config) -> Result<(), CLIError> {
+    let filter = parse_filter(&directives(config))?;
+    let (filter, handle) = reload::Layer::new(filter);
+    let registry = tracing_subscriber::registry().with(filter);
+
+    let (writer, ansi) = match &config.log_file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| CLIError::writer(Path::new(path), e))?;
+            (BoxMakeWriter::new(Mutex::new(file)), false)
+        }
+        // Colours only make sense on a terminal, not in CI logs
+        None => (BoxMakeWriter::new(io::stdout), io::stdout().is_terminal()),
+    };
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
+
+    match config.log_format {
+        LogFormat::Text => registry.with(layer).try_init(),
+        LogFormat::Json => registry.with(layer.json().with_span_list(true)).try_init(),
+    }
+    .map_err(|e| CLIError::resource_busy("cannot install the logger", e))?;
+    let _ = FILTER.set(handle);
+    sync_log_level();
+
+    Ok(())
+}
+
+/// The filter directives for `config`: the log level, then `RUST_LOG`, then `config.log_filter`.
+/// Later directives for the same target take precedence.
+pub fn directives(config: &Config) -> String {
+    [
+        Some(config.log_level.clone()),
+        env::var("RUST_LOG").ok(),
+        config.log_filter.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|directives| !directives.is_empty())
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// The log level `verbosity` steps above the default info level, e.g. debug for 1 or warn for -1.
+pub fn verbosity_level(verbosity: i8) -> LevelFilter {
+    match verbosity {
+        i8::MIN..=-3 => LevelFilter::OFF,
+        -2 => LevelFilter::ERROR,
+        -1 => LevelFilter::WARN,
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        2..=i8::MAX => LevelFilter::TRACE,
+    }
+}
+
+/// The directives of the current filter, `None` if no subscriber was installed through [`init`].
+pub fn filter() -> Option<String> {
+    FILTER.get()?.with_current(|filter| filter.to_string()).ok()
+}
+
+/// Replace the filter with `directives`, e.g. `info,libp2p_kad=debug`.
+/// Does nothing if no subscriber was installed through [`init`].
+pub fn set_filter(directives: &str) -> Result<(), CLIError> {
+    let filter = parse_filter(directives)?;
+    if let Some(handle) = FILTER.get() {
+        handle
+            .reload(filter)
+            .map_err(|e| CLIError::resource_busy("cannot change the log filter", e))?;
+        sync_log_level();
+    }
+
+    Ok(())
+}
+
+/// Change the maximum log level of every target.
+pub fn set_level(level: LevelFilter) -> Result<(), CLIError> {
+    set_filter(&level.to_string())
+}
+
+/// Parse `directives`, naming the first directive that is invalid on its own if they do not parse.
+fn parse_filter(directives: &str) -> Result<EnvFilter, CLIError> {
+    EnvFilter::try_new(directives).map_err(|e| CLIError::InvalidLogFilter {
+        directive: directives
+            .split(',')
+            .find(|directive| EnvFilter::try_new(directive).is_err())
+            .unwrap_or(directives)
+            .to_string(),
+        reason: e.to_string(),
+    })
+}
+
+/// Records of the `log` crate are dropped before they reach the subscriber if they are above
+/// its own maximum level, which is only set when the subscriber is installed.
+fn sync_log_level() {
+    log::set_max_level(
+        LevelFilter::current()
+            .to_string()
+            .parse()
+            .unwrap_or(log::LevelFilter::Trace),
+    );
+}
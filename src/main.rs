@@ -1,17 +1,22 @@
 //! `libp2p_test_tool` is a CLI tool to interact with a libp2p network and generate a network
 //! report for both unreachable and reachable peers.
 
-use clap::Parser;
-use env_logger::Env;
-use log::info;
+use clap::{ArgAction, Args, Parser};
+use futures::StreamExt;
+use std::{error::Error, net::SocketAddr, process::ExitCode, time::Duration};
+#[cfg(feature = "tui")]
+use tracing::warn;
+use tracing::{debug, info};
 
-mod behaviour;
-mod config;
-mod controller;
-mod error;
-mod writer;
-
-use crate::{config::Config, controller::Controller, error::CLIError};
+#[cfg(feature = "tui")]
+use libp2p_test_tool::tui;
+use libp2p_test_tool::{
+    bootstrap::{read_bootstrap_file, resolve_dnsaddr},
+    diff::{PeerSnapshot, ReportDiff},
+    logging,
+    preflight::{self, Preflight},
+    CLIError, Config, Controller, CsvNaming, LogFormat, MdnsMode, Mode, SinkKind, TimestampFormat,
+};
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -25,15 +30,159 @@ use crate::{config::Config, controller::Controller, error::CLIError};
 struct Opt {
     #[clap(subcommand)]
     argument: CliArgument,
+
+    #[clap(flatten)]
+    log_options: LogOptions,
+
+    /// Skip the run summary at the end of the run, also implied by -q.
+    #[clap(long = "no-summary", global = true, verbatim_doc_comment)]
+    no_summary: bool,
+
+    /// Show a live dashboard of the run in the terminal instead of the log output.
+    #[cfg(feature = "tui")]
+    #[clap(long = "tui", global = true, verbatim_doc_comment)]
+    tui: bool,
 }
 
 #[derive(Debug, Parser)]
 enum CliArgument {
     GenerateNetworkReport {
-        /// A set of bootstrapped libp2p node addresses from the network you want to generate metrics from.
-        /// If not supplied the tool falls back to dialing the Avail network bootstrapped node.
-        #[clap(long = "bootstrap-node-addrs", display_order = 1, verbatim_doc_comment)]
-        bootstrap_node_addrs: Option<String>,
+        #[clap(flatten)]
+        bootstrap_options: BootstrapOptions,
+
+        /// Path to store a CSV report on newly connected nodes in the network.
+        #[clap(long = "just-connected", display_order = 2, verbatim_doc_comment)]
+        just_connected: Option<String>,
+
+        /// Path to store a CSV report on non-reachable nodes in the network.
+        #[clap(long = "unreachable", display_order = 3, verbatim_doc_comment)]
+        unreachable: Option<String>,
+
+        /// Path to store the discovered network topology as a GraphViz DOT file.
+        #[clap(long = "topology-dot", display_order = 4, verbatim_doc_comment)]
+        topology_dot: Option<String>,
+
+        /// Path to store the discovered network topology as a GraphML file.
+        #[clap(long = "topology-graphml", display_order = 5, verbatim_doc_comment)]
+        topology_graphml: Option<String>,
+
+        /// Path to store the network report with the connectivity analysis.
+        #[clap(long = "network-report", display_order = 6, verbatim_doc_comment)]
+        network_report: Option<String>,
+
+        /// Path to store a CSV report on peers joining and leaving the network.
+        #[clap(long = "churn", display_order = 7, verbatim_doc_comment)]
+        churn: Option<String>,
+
+        /// Whether to discover peers through mdns: auto, on or off.
+        /// With auto, mdns only runs when every bootstrap node is on a private network.
+        #[clap(long = "mdns", display_order = 8, verbatim_doc_comment)]
+        mdns: Option<MdnsMode>,
+
+        #[clap(flatten)]
+        mdns_options: MdnsOptions,
+
+        #[clap(flatten)]
+        api_options: ApiOptions,
+
+        #[clap(flatten)]
+        sink_options: SinkOptions,
+    },
+    EstimateNetworkSize {
+        #[clap(flatten)]
+        bootstrap_options: BootstrapOptions,
+
+        /// Number of random DHT lookups to sample before the estimate is reported.
+        #[clap(long = "samples", display_order = 2, verbatim_doc_comment)]
+        samples: Option<usize>,
+
+        /// Path to store a CSV report on newly connected nodes in the network.
+        #[clap(long = "just-connected", display_order = 3, verbatim_doc_comment)]
+        just_connected: Option<String>,
+
+        /// Path to store a CSV report on non-reachable nodes in the network.
+        #[clap(long = "unreachable", display_order = 4, verbatim_doc_comment)]
+        unreachable: Option<String>,
+
+        /// Path to store the discovered network topology as a GraphViz DOT file.
+        #[clap(long = "topology-dot", display_order = 5, verbatim_doc_comment)]
+        topology_dot: Option<String>,
+
+        /// Path to store the discovered network topology as a GraphML file.
+        #[clap(long = "topology-graphml", display_order = 6, verbatim_doc_comment)]
+        topology_graphml: Option<String>,
+
+        /// Path to store the network report with the size estimate.
+        #[clap(long = "network-report", display_order = 7, verbatim_doc_comment)]
+        network_report: Option<String>,
+
+        /// Path to store a CSV report on peers joining and leaving the network.
+        #[clap(long = "churn", display_order = 8, verbatim_doc_comment)]
+        churn: Option<String>,
+
+        /// Whether to discover peers through mdns: auto, on or off.
+        /// With auto, mdns only runs when every bootstrap node is on a private network.
+        #[clap(long = "mdns", display_order = 9, verbatim_doc_comment)]
+        mdns: Option<MdnsMode>,
+
+        #[clap(flatten)]
+        mdns_options: MdnsOptions,
+
+        #[clap(flatten)]
+        api_options: ApiOptions,
+
+        #[clap(flatten)]
+        sink_options: SinkOptions,
+    },
+    SurveyLocalNetwork {
+        /// Path to store a CSV report on newly connected nodes in the local network.
+        #[clap(long = "just-connected", display_order = 1, verbatim_doc_comment)]
+        just_connected: Option<String>,
+
+        /// Path to store a CSV report on non-reachable nodes in the local network.
+        #[clap(long = "unreachable", display_order = 2, verbatim_doc_comment)]
+        unreachable: Option<String>,
+
+        /// Path to store the network report for the local network.
+        #[clap(long = "network-report", display_order = 3, verbatim_doc_comment)]
+        network_report: Option<String>,
+
+        #[clap(flatten)]
+        mdns_options: MdnsOptions,
+
+        #[clap(flatten)]
+        api_options: ApiOptions,
+
+        #[clap(flatten)]
+        sink_options: SinkOptions,
+    },
+    Diff {
+        /// Reports of the earlier run: newly connected and unreachable CSVs or a JSON report,
+        /// separated by commas.
+        #[clap(
+            long = "before",
+            required = true,
+            value_delimiter = ',',
+            display_order = 1,
+            verbatim_doc_comment
+        )]
+        before: Vec<String>,
+
+        /// Reports of the later run, in the same formats as --before.
+        #[clap(
+            long = "after",
+            required = true,
+            value_delimiter = ',',
+            display_order = 2,
+            verbatim_doc_comment
+        )]
+        after: Vec<String>,
+    },
+    /// Check the configuration of a run without running it: bootstrap addresses, output paths,
+    /// listen addresses and one dial to each bootstrap node.
+    Check {
+        #[clap(flatten)]
+        bootstrap_options: BootstrapOptions,
 
         /// Path to store a CSV report on newly connected nodes in the network.
         #[clap(long = "just-connected", display_order = 2, verbatim_doc_comment)]
@@ -42,52 +191,456 @@ enum CliArgument {
         /// Path to store a CSV report on non-reachable nodes in the network.
         #[clap(long = "unreachable", display_order = 3, verbatim_doc_comment)]
         unreachable: Option<String>,
+
+        /// Path to store the discovered network topology as a GraphViz DOT file.
+        #[clap(long = "topology-dot", display_order = 4, verbatim_doc_comment)]
+        topology_dot: Option<String>,
+
+        /// Path to store the discovered network topology as a GraphML file.
+        #[clap(long = "topology-graphml", display_order = 5, verbatim_doc_comment)]
+        topology_graphml: Option<String>,
+
+        /// Path to store the network report with the connectivity analysis.
+        #[clap(long = "network-report", display_order = 6, verbatim_doc_comment)]
+        network_report: Option<String>,
+
+        /// Path to store a CSV report on peers joining and leaving the network.
+        #[clap(long = "churn", display_order = 7, verbatim_doc_comment)]
+        churn: Option<String>,
+
+        /// Time to wait for the bootstrap nodes to answer a dial (in s). Defaults to 5.
+        #[clap(long = "dial-timeout", display_order = 8, verbatim_doc_comment)]
+        dial_timeout: Option<u64>,
+
+        #[clap(flatten)]
+        api_options: ApiOptions,
+
+        #[clap(flatten)]
+        sink_options: SinkOptions,
     },
 }
 
+#[derive(Debug, Args)]
+struct BootstrapOptions {
+    /// A set of bootstrapped libp2p node addresses from the network you want to generate metrics from.
+    /// If not supplied the tool falls back to dialing the Avail network bootstrapped node.
+    #[clap(long = "bootstrap-node-addrs", display_order = 1, verbatim_doc_comment)]
+    bootstrap_node_addrs: Option<String>,
+
+    /// Path to a file with one bootstrap node address per line.
+    /// Empty lines and lines starting with # are ignored.
+    #[clap(long = "bootstrap-file", display_order = 10, verbatim_doc_comment)]
+    bootstrap_file: Option<String>,
+
+    /// Domain whose _dnsaddr TXT records list bootstrap node addresses.
+    #[clap(long = "bootstrap-dnsaddr", display_order = 11, verbatim_doc_comment)]
+    bootstrap_dnsaddr: Option<String>,
+
+    /// Continue with the valid bootstrap node addresses if some of them are invalid.
+    #[clap(
+        long = "skip-invalid-bootstrap",
+        display_order = 12,
+        verbatim_doc_comment
+    )]
+    skip_invalid_bootstrap: bool,
+}
+
+impl BootstrapOptions {
+    /// Collect bootstrap addresses from every source given into `config`.
+    /// The default bootstrap node is only kept if no source is given.
+    /// The addresses are validated by the controller before it starts.
+    async fn apply(self, config: &mut Config) -> Result<(), CLIError> {
+        let mut bootstrap_addrs = Vec::new();
+        let mut supplied = false;
+
+        if let Some(addrs) = self.bootstrap_node_addrs {
+            bootstrap_addrs.extend(split_bootstrap_addrs(&addrs));
+            supplied = true;
+        }
+        if let Some(path) = self.bootstrap_file {
+            bootstrap_addrs.extend(read_bootstrap_file(&path)?);
+            supplied = true;
+        }
+        if let Some(domain) = self.bootstrap_dnsaddr {
+            bootstrap_addrs.extend(resolve_dnsaddr(&domain).await?);
+            supplied = true;
+        }
+
+        if supplied {
+            config.bootstrap_addr = bootstrap_addrs;
+        }
+        config.skip_invalid_bootstrap = self.skip_invalid_bootstrap;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+struct LogOptions {
+    /// Log more: debug, or trace if given twice.
+    #[clap(short = 'v', long = "verbose", global = true, action = ArgAction::Count, verbatim_doc_comment)]
+    verbose: u8,
+
+    /// Log less: warn, error if given twice, nothing if given three times.
+    /// Also skips the run summary at the end of the run.
+    #[clap(short = 'q', long = "quiet", global = true, action = ArgAction::Count, verbatim_doc_comment)]
+    quiet: u8,
+
+    /// Per-module log filter directives on top of the log level, e.g. libp2p_kad=debug,libp2p_swarm=warn.
+    /// They also take precedence over RUST_LOG.
+    #[clap(long = "log-filter", global = true, verbatim_doc_comment)]
+    log_filter: Option<String>,
+
+    /// File to append the log output to instead of stdout.
+    #[clap(long = "log-file", global = true, verbatim_doc_comment)]
+    log_file: Option<String>,
+
+    /// Format of the log output: text or json.
+    /// JSON lines carry the fields of each event and of the peer or query span it belongs to.
+    #[clap(long = "log-format", global = true, verbatim_doc_comment)]
+    log_format: Option<LogFormat>,
+}
+
+impl LogOptions {
+    /// Apply log options on top of `config`.
+    fn apply(self, config: &mut Config) {
+        if self.verbose > 0 || self.quiet > 0 {
+            let verbosity = self.verbose.min(3) as i8 - self.quiet.min(3) as i8;
+            config.log_level = logging::verbosity_level(verbosity).to_string();
+        }
+        config.log_filter = self.log_filter.or(config.log_filter.take());
+        config.log_file = self.log_file.or(config.log_file.take());
+        config.log_format = self.log_format.unwrap_or(config.log_format);
+    }
+}
+
+#[derive(Debug, Args)]
+struct MdnsOptions {
+    /// Time to live of discovered mdns records (in s).
+    #[clap(long = "mdns-ttl", display_order = 20, verbatim_doc_comment)]
+    mdns_ttl: Option<u64>,
+
+    /// Interval between mdns queries (in s).
+    #[clap(long = "mdns-query-interval", display_order = 21, verbatim_doc_comment)]
+    mdns_query_interval: Option<u64>,
+
+    /// Query IPv6 instead of IPv4 interfaces through mdns.
+    #[clap(long = "mdns-ipv6", display_order = 22, verbatim_doc_comment)]
+    mdns_ipv6: bool,
+}
+
+#[derive(Debug, Args)]
+struct ApiOptions {
+    /// Local address to serve the HTTP API on while the tool runs, e.g. 127.0.0.1:7080.
+    /// The API is disabled unless this is set.
+    #[clap(long = "http-api", display_order = 30, verbatim_doc_comment)]
+    http_api: Option<SocketAddr>,
+    /// File to dump the Prometheus metrics to at the end of the run,
+    /// in the same format as the /metrics endpoint of the HTTP API.
+    #[clap(long = "metrics-file", display_order = 31, verbatim_doc_comment)]
+    metrics_file: Option<String>,
+}
+
+impl ApiOptions {
+    /// Apply HTTP API and metrics options on top of `config`.
+    fn apply(self, config: &mut Config) {
+        config.http_api = self.http_api.or(config.http_api);
+        config.metrics_file = self.metrics_file.or(config.metrics_file.take());
+    }
+}
+
+#[derive(Debug, Args)]
+struct SinkOptions {
+    /// Where to write the peer tables and report sections, can be repeated:
+    /// csv (the --just-connected and --unreachable files), stdout, json=<PATH> or sqlite=<PATH>.
+    /// Defaults to csv.
+    #[clap(
+        long = "sink",
+        display_order = 40,
+        value_delimiter = ',',
+        verbatim_doc_comment
+    )]
+    sinks: Vec<SinkKind>,
+
+    /// SQLite database to keep every run and peer observation in, so peers can be
+    /// followed across runs. The database is created if it does not exist.
+    #[clap(long = "history-db", display_order = 41, verbatim_doc_comment)]
+    history_db: Option<String>,
+
+    /// Append to existing CSV reports instead of replacing them. Files with other
+    /// columns are rotated first.
+    #[clap(long = "csv-append", display_order = 42, verbatim_doc_comment)]
+    csv_append: bool,
+
+    /// Add the start time of the run to the CSV report file names, so every run
+    /// writes new files.
+    #[clap(
+        long = "csv-timestamp",
+        display_order = 43,
+        conflicts_with = "csv_rotate_interval",
+        verbatim_doc_comment
+    )]
+    csv_timestamp: bool,
+
+    /// Add the start of the current interval (in s) to the CSV report file names,
+    /// so a new set of files is started every interval.
+    #[clap(
+        long = "csv-rotate-interval",
+        display_order = 44,
+        value_parser = clap::value_parser!(u64).range(1..),
+        verbatim_doc_comment
+    )]
+    csv_rotate_interval: Option<u64>,
+
+    /// Rotate a CSV report to <NAME>.<N>.csv once it reaches this size (in bytes).
+    #[clap(long = "csv-rotate-size", display_order = 45, verbatim_doc_comment)]
+    csv_rotate_size: Option<u64>,
+
+    /// Format of the timestamps in reports and the HTTP API, all in UTC:
+    /// rfc3339 (e.g. 2023-11-12T09:30:00.123Z) or epoch-ms. Defaults to rfc3339.
+    #[clap(long = "timestamp-format", display_order = 46, verbatim_doc_comment)]
+    timestamp_format: Option<TimestampFormat>,
+}
+
+impl SinkOptions {
+    /// Apply report sink and history options on top of `config`.
+    fn apply(self, config: &mut Config) {
+        if !self.sinks.is_empty() {
+            config.sinks = self.sinks;
+        }
+        config.history_db = self.history_db.or(config.history_db.take());
+        config.csv_append |= self.csv_append;
+        if self.csv_timestamp {
+            config.csv_naming = CsvNaming::PerRun;
+        }
+        if let Some(interval) = self.csv_rotate_interval {
+            config.csv_naming = CsvNaming::Interval(interval);
+        }
+        config.csv_max_size = self.csv_rotate_size.or(config.csv_max_size);
+        config.timestamp_format = self.timestamp_format.unwrap_or(config.timestamp_format);
+    }
+}
+
+impl MdnsOptions {
+    /// Apply mdns options on top of `config`.
+    fn apply(self, config: &mut Config) {
+        config.mdns_ttl = self.mdns_ttl.unwrap_or(config.mdns_ttl);
+        config.mdns_query_interval = self
+            .mdns_query_interval
+            .unwrap_or(config.mdns_query_interval);
+        config.mdns_ipv6 = self.mdns_ipv6;
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<(), CLIError> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            let mut source = e.source();
+            while let Some(cause) = source {
+                eprintln!("  Caused by: {}", cause);
+                source = cause.source();
+            }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+async fn run() -> Result<(), CLIError> {
     ///////////////////////////
     // 1. Prepare environment.
     ///////////////////////////
 
     let mut config: Config = Config::default();
 
-    env_logger::Builder::from_env(Env::default().default_filter_or(&config.log_level.clone()))
-        .init();
+    let opt = Opt::parse();
+    let no_summary = opt.no_summary || opt.log_options.quiet > 0;
+    opt.log_options.apply(&mut config);
+    logging::init(&config)?;
 
-    let CliArgument::GenerateNetworkReport {
-        unreachable,
-        just_connected,
-        bootstrap_node_addrs,
-    } = Opt::parse().argument;
+    match opt.argument {
+        CliArgument::GenerateNetworkReport {
+            unreachable,
+            just_connected,
+            bootstrap_options,
+            topology_dot,
+            topology_graphml,
+            network_report,
+            churn,
+            mdns,
+            mdns_options,
+            api_options,
+            sink_options,
+        } => {
+            bootstrap_options.apply(&mut config).await?;
+            config.mdns = mdns.unwrap_or(config.mdns);
+            mdns_options.apply(&mut config);
+            api_options.apply(&mut config);
+            sink_options.apply(&mut config);
+            config.churn_csv = churn.unwrap_or(config.churn_csv);
+            config.unreachable_csv = unreachable.unwrap_or(config.unreachable_csv);
+            config.just_connected = just_connected.unwrap_or(config.just_connected);
+            config.topology_dot = topology_dot.unwrap_or(config.topology_dot);
+            config.topology_graphml = topology_graphml.unwrap_or(config.topology_graphml);
+            config.network_report = network_report.unwrap_or(config.network_report);
+        }
+        CliArgument::EstimateNetworkSize {
+            bootstrap_options,
+            samples,
+            just_connected,
+            unreachable,
+            topology_dot,
+            topology_graphml,
+            network_report,
+            churn,
+            mdns,
+            mdns_options,
+            api_options,
+            sink_options,
+        } => {
+            bootstrap_options.apply(&mut config).await?;
+            config.mdns = mdns.unwrap_or(config.mdns);
+            mdns_options.apply(&mut config);
+            api_options.apply(&mut config);
+            sink_options.apply(&mut config);
+            config.mode = Mode::EstimateNetworkSize;
+            config.size_estimation_samples = samples.unwrap_or(config.size_estimation_samples);
+            config.churn_csv = churn.unwrap_or(config.churn_csv);
+            config.unreachable_csv = unreachable.unwrap_or(config.unreachable_csv);
+            config.just_connected = just_connected.unwrap_or(config.just_connected);
+            config.topology_dot = topology_dot.unwrap_or(config.topology_dot);
+            config.topology_graphml = topology_graphml.unwrap_or(config.topology_graphml);
+            config.network_report = network_report.unwrap_or(config.network_report);
+        }
+        CliArgument::SurveyLocalNetwork {
+            just_connected,
+            unreachable,
+            network_report,
+            mdns_options,
+            api_options,
+            sink_options,
+        } => {
+            config.mode = Mode::LanSurvey;
+            config.mdns = MdnsMode::Enabled;
+            config.bootstrap_addr = Vec::new();
+            mdns_options.apply(&mut config);
+            api_options.apply(&mut config);
+            sink_options.apply(&mut config);
+            config.just_connected = just_connected.unwrap_or(config.just_connected);
+            config.unreachable_csv = unreachable.unwrap_or(config.unreachable_csv);
+            config.network_report = network_report.unwrap_or(config.network_report);
+        }
+        CliArgument::Diff { before, after } => {
+            // Comparing reports needs no network interaction
+            let diff = ReportDiff::between(
+                &PeerSnapshot::from_files(&before)?,
+                &PeerSnapshot::from_files(&after)?,
+            );
+            print!("{}", diff.to_report().render());
 
-    if let Some(bootstrap_addrs) = bootstrap_node_addrs {
-        let bootstrap_addrs: Vec<String> = bootstrap_addrs
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .collect();
+            return Ok(());
+        }
+        CliArgument::Check {
+            bootstrap_options,
+            just_connected,
+            unreachable,
+            topology_dot,
+            topology_graphml,
+            network_report,
+            churn,
+            dial_timeout,
+            api_options,
+            sink_options,
+        } => {
+            // Invalid bootstrap addresses are reported together with every other check
+            bootstrap_options.apply(&mut config).await?;
+            api_options.apply(&mut config);
+            sink_options.apply(&mut config);
+            config.churn_csv = churn.unwrap_or(config.churn_csv);
+            config.unreachable_csv = unreachable.unwrap_or(config.unreachable_csv);
+            config.just_connected = just_connected.unwrap_or(config.just_connected);
+            config.topology_dot = topology_dot.unwrap_or(config.topology_dot);
+            config.topology_graphml = topology_graphml.unwrap_or(config.topology_graphml);
+            config.network_report = network_report.unwrap_or(config.network_report);
 
-        config.bootstrap_addr = bootstrap_addrs;
-    };
-    config.unreachable_csv = unreachable.unwrap_or(config.unreachable_csv);
-    config.just_connected = just_connected.unwrap_or(config.just_connected);
+            let dial_timeout = dial_timeout.unwrap_or(preflight::DEFAULT_DIAL_TIMEOUT);
+            let preflight = Preflight::run(&config, Duration::from_secs(dial_timeout)).await;
+            print!("{}", preflight.to_report().render());
+
+            return preflight.into_result();
+        }
+    }
 
     /////////////////////////////
     // 2. Start Swarm Controller.
     /////////////////////////////
 
     info!("Starting to interact with the chosen libp2p network...");
-    let result = Controller::new(config).await?.start().await;
-
-    if let Ok(controller) = result {
-        info!(
-            "Libp2p Network Interaction complete. The results are saved in {} and {}",
-            &controller.config.just_connected, &controller.config.unreachable_csv
-        );
-    } else if let Err(e) = result {
-        info!("Error: {:?}", e);
+    let mut controller = Controller::new(config).await?;
+
+    let mut events = controller.events();
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            debug!(event = ?event, "Controller event");
+        }
+    });
+
+    // An interrupted run still writes its results
+    let handle = controller.handle();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("Interrupted, stopping the network interaction");
+            handle.stop();
+        }
+    });
+
+    #[cfg(feature = "tui")]
+    let dashboard = opt
+        .tui
+        .then(|| tokio::spawn(tui::Dashboard::new(&mut controller).run()));
+
+    let controller = controller.start().await?;
+
+    // The dashboard closes once the controller has stopped
+    #[cfg(feature = "tui")]
+    if let Some(dashboard) = dashboard {
+        if let Ok(Err(e)) = dashboard.await {
+            warn!(error = %e, "Dashboard failed");
+        }
+    }
+
+    // The stdout sink already printed its own run summary with the report
+    let stdout_sink = controller.config.sinks.contains(&SinkKind::Stdout);
+    if let Some(summary) = controller
+        .summary
+        .as_ref()
+        .filter(|_| !no_summary && !stdout_sink)
+    {
+        print!("{}", summary.to_report().render());
     }
 
+    info!(
+        "Libp2p Network Interaction complete. The results are saved in {} and {}",
+        &controller.config.just_connected, &controller.config.unreachable_csv
+    );
+    info!(
+        "The network topology is saved in {} and {}",
+        &controller.config.topology_dot, &controller.config.topology_graphml
+    );
+    info!(
+        "The network report is saved in {} and churn events in {}",
+        &controller.config.network_report, &controller.config.churn_csv
+    );
+
     Ok(())
 }
+
+/// Split a comma separated list of bootstrap addresses.
+fn split_bootstrap_addrs(bootstrap_addrs: &str) -> Vec<String> {
+    bootstrap_addrs
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .collect()
+}
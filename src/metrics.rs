@@ -0,0 +1,203 @@
+//! Prometheus metrics derived from swarm events, served on `/metrics` by the HTTP API.
+//! Identify, Kademlia, Ping and swarm events are also recorded by libp2p's own
+//! metrics under the `libp2p_test_tool_libp2p` prefix.
+
+use libp2p::{
+    metrics::{Metrics as Libp2pMetrics, Recorder},
+    swarm::DialError,
+};
+use prometheus_client::{
+    encoding::{text::encode, EncodeLabelSet},
+    metrics::{counter::Counter, family::Family, gauge::Gauge},
+    registry::Registry,
+};
+use std::{fs::File, io::Write, path::PathBuf};
+
+use crate::{behaviour::PeerNetworkEvent, error::CLIError};
+
+pub struct Metrics {
+    registry: Registry,
+    connected_peers: Gauge,
+    dial_attempts: Counter,
+    dial_failures: Family<DialFailureLabels, Counter>,
+    identify_received: Counter,
+    kad_queries: Family<QueryLabels, Counter>,
+    mdns_discoveries: Counter,
+    kbucket_entries: Family<BucketLabels, Gauge>,
+    libp2p: Libp2pMetrics,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct DialFailureLabels {
+    error: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct QueryLabels {
+    query: &'static str,
+    outcome: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct BucketLabels {
+    bucket: u32,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::with_prefix(METRICS_PREFIX);
+
+        let connected_peers = Gauge::default();
+        registry.register(
+            "connected_peers",
+            "Number of peers with at least one open connection",
+            connected_peers.clone(),
+        );
+        let dial_attempts = Counter::default();
+        registry.register(
+            "dial_attempts",
+            "Number of outgoing dials",
+            dial_attempts.clone(),
+        );
+        let dial_failures = Family::default();
+        registry.register(
+            "dial_failures",
+            "Number of failed outgoing dials by error class",
+            dial_failures.clone(),
+        );
+        let identify_received = Counter::default();
+        registry.register(
+            "identify_received",
+            "Number of identify responses received",
+            identify_received.clone(),
+        );
+        let kad_queries = Family::default();
+        registry.register(
+            "kad_queries",
+            "Number of finished Kademlia queries by query type and outcome",
+            kad_queries.clone(),
+        );
+        let mdns_discoveries = Counter::default();
+        registry.register(
+            "mdns_discoveries",
+            "Number of peers discovered through mdns",
+            mdns_discoveries.clone(),
+        );
+        let kbucket_entries = Family::default();
+        registry.register(
+            "kbucket_entries",
+            "Number of peers in each non-empty Kademlia bucket",
+            kbucket_entries.clone(),
+        );
+        let libp2p = Libp2pMetrics::new(&mut registry);
+
+        Metrics {
+            registry,
+            connected_peers,
+            dial_attempts,
+            dial_failures,
+            identify_received,
+            kad_queries,
+            mdns_discoveries,
+            kbucket_entries,
+            libp2p,
+        }
+    }
+
+    /// Record a protocol or swarm event in libp2p's own metrics.
+    pub(crate) fn record<E>(&self, event: &E)
+    where
+        Libp2pMetrics: Recorder<E>,
+    {
+        self.libp2p.record(event);
+    }
+
+    /// Record a behaviour event in libp2p's own metrics. Mdns has no libp2p metrics.
+    pub(crate) fn record_behaviour(&self, event: &PeerNetworkEvent) {
+        match event {
+            PeerNetworkEvent::Identify(event) => self.record(event),
+            PeerNetworkEvent::Kad(event) => self.record(event),
+            PeerNetworkEvent::Ping(event) => self.record(event),
+            PeerNetworkEvent::Mdns(_) => {}
+        }
+    }
+
+    pub(crate) fn set_connected_peers(&self, count: usize) {
+        self.connected_peers.set(count as i64);
+    }
+
+    pub(crate) fn on_dial(&self) {
+        self.dial_attempts.inc();
+    }
+
+    pub(crate) fn on_dial_failure(&self, error: &DialError) {
+        self.dial_failures
+            .get_or_create(&DialFailureLabels {
+                error: dial_error_class(error),
+            })
+            .inc();
+    }
+
+    pub(crate) fn on_identify(&self) {
+        self.identify_received.inc();
+    }
+
+    pub(crate) fn on_query(&self, query: &'static str, outcome: &'static str) {
+        self.kad_queries
+            .get_or_create(&QueryLabels { query, outcome })
+            .inc();
+    }
+
+    pub(crate) fn on_mdns_discovery(&self) {
+        self.mdns_discoveries.inc();
+    }
+
+    /// Replace the bucket sizes with `(Bucket index, Number of entries)` pairs.
+    pub(crate) fn set_bucket_sizes(&self, sizes: Vec<(u32, usize)>) {
+        self.kbucket_entries.clear();
+        for (bucket, entries) in sizes {
+            self.kbucket_entries
+                .get_or_create(&BucketLabels { bucket })
+                .set(entries as i64);
+        }
+    }
+
+    /// Encode every metric in the Prometheus text format.
+    pub fn encode(&self) -> String {
+        let mut buffer = String::new();
+        let _ = encode(&mut buffer, &self.registry);
+
+        buffer
+    }
+
+    /// Dump every metric to `path` in the Prometheus text format.
+    pub(crate) fn write_to_file(&self, path: PathBuf) -> Result<(), CLIError> {
+        let mut file = File::create(&path).map_err(|e| CLIError::writer(&path, e))?;
+        file.write_all(self.encode().as_bytes())
+            .map_err(|e| CLIError::writer(&path, e))?;
+        file.flush().map_err(|e| CLIError::writer(&path, e))?;
+
+        Ok(())
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The class a failed dial is counted under, e.g. `transport` or `wrong_peer_id`.
+pub(crate) fn dial_error_class(error: &DialError) -> &'static str {
+    match error {
+        DialError::LocalPeerId { .. } => "local_peer_id",
+        DialError::NoAddresses => "no_addresses",
+        DialError::DialPeerConditionFalse(_) => "condition_false",
+        DialError::Aborted => "aborted",
+        DialError::WrongPeerId { .. } => "wrong_peer_id",
+        DialError::Denied { .. } => "denied",
+        DialError::Transport(_) => "transport",
+    }
+}
+
+const METRICS_PREFIX: &str = "libp2p_test_tool";
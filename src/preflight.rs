@@ -0,0 +1,283 @@
+//! Preflight checks run by the `check` subcommand, so a misconfigured run fails in seconds
+//! instead of at the end of the run.
+
+use futures::StreamExt;
+use libp2p::{
+    multiaddr::Protocol,
+    swarm::{dial_opts::DialOpts, dummy, ConnectionId, SwarmEvent},
+    Multiaddr, Swarm,
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{self, File, OpenOptions},
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    path::Path,
+    time::Duration,
+};
+use tokio::time;
+
+use crate::{
+    bootstrap::validate_bootstrap_addr,
+    config::{Config, SinkKind},
+    controller::Controller,
+    error::CLIError,
+    report::{NetworkReport, ReportSection},
+};
+
+/// Outcome of a single check.
+#[derive(Debug)]
+pub struct PreflightCheck {
+    /// Report section the check is listed in
+    pub section: &'static str,
+    /// What was checked, e.g. a path or an address
+    pub name: String,
+    /// What was found if the check passed
+    pub result: Result<String, CLIError>,
+}
+
+/// Every check of a configuration, in the order they were run.
+#[derive(Debug, Default)]
+pub struct Preflight {
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl Preflight {
+    /// Check the bootstrap addresses, output paths and listen addresses of `config`, then dial
+    /// every valid bootstrap node once, waiting at most `dial_timeout` for all of them.
+    /// Nothing is written to the output paths.
+    pub async fn run(config: &Config, dial_timeout: Duration) -> Self {
+        let mut preflight = Preflight::default();
+
+        let mut bootstrap_addrs = Vec::new();
+        for addr in config.bootstrap_addr.iter() {
+            let result = match validate_bootstrap_addr(addr) {
+                Ok(multi_addr) => {
+                    let detail = if multi_addr
+                        .iter()
+                        .any(|protocol| matches!(protocol, Protocol::P2p(_)))
+                    {
+                        String::from("valid")
+                    } else {
+                        String::from("valid, its PeerId cannot be verified")
+                    };
+                    bootstrap_addrs.push(multi_addr);
+                    Ok(detail)
+                }
+                Err(e) if config.skip_invalid_bootstrap => Ok(format!("skipped: {}", e)),
+                Err(e) => Err(e),
+            };
+            preflight.push(BOOTSTRAP_ADDRS, addr, result);
+        }
+
+        for path in output_paths(config) {
+            preflight.push(OUTPUT_PATHS, path, check_writable(Path::new(path)));
+        }
+
+        let listening_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, config.listening_port));
+        preflight.push(
+            LISTEN_ADDRS,
+            &listening_addr.to_string(),
+            check_listen(listening_addr),
+        );
+        if let Some(http_api) = config.http_api {
+            preflight.push(LISTEN_ADDRS, &http_api.to_string(), check_listen(http_api));
+        }
+
+        for (addr, result) in dial_bootstrap_nodes(config, bootstrap_addrs, dial_timeout).await {
+            preflight.push(BOOTSTRAP_DIALS, &addr, result);
+        }
+
+        preflight
+    }
+
+    /// Whether every check passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.result.is_ok())
+    }
+
+    /// One section per kind of check, with the overall result first.
+    pub fn to_report(&self) -> NetworkReport {
+        let mut report = NetworkReport::new();
+
+        let mut summary = ReportSection::new("Preflight Check");
+        summary.push("Result", if self.passed() { "Passed" } else { "Failed" });
+        summary.push(
+            "Failed checks",
+            self.checks
+                .iter()
+                .filter(|check| check.result.is_err())
+                .count(),
+        );
+        report.push(summary);
+
+        for title in [BOOTSTRAP_ADDRS, OUTPUT_PATHS, LISTEN_ADDRS, BOOTSTRAP_DIALS] {
+            let mut section = ReportSection::new(title);
+            for check in self.checks.iter().filter(|check| check.section == title) {
+                match &check.result {
+                    Ok(detail) => section.push(&check.name, detail),
+                    Err(e) => match e.source() {
+                        Some(cause) => {
+                            section.push(&check.name, format!("FAILED: {} ({})", e, cause))
+                        }
+                        None => section.push(&check.name, format!("FAILED: {}", e)),
+                    },
+                }
+            }
+            report.push(section);
+        }
+
+        report
+    }
+
+    /// The error of the first failed check, so the exit code tells its class.
+    pub fn into_result(self) -> Result<(), CLIError> {
+        match self.checks.into_iter().find_map(|check| check.result.err()) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn push(&mut self, section: &'static str, name: &str, result: Result<String, CLIError>) {
+        self.checks.push(PreflightCheck {
+            section,
+            name: name.to_string(),
+            result,
+        });
+    }
+}
+
+/// Every file a run of `config` writes to.
+fn output_paths(config: &Config) -> Vec<&str> {
+    let mut paths = Vec::new();
+
+    for sink in config.sinks.iter() {
+        match sink {
+            SinkKind::Csv => {
+                paths.push(config.just_connected.as_str());
+                paths.push(config.unreachable_csv.as_str());
+            }
+            SinkKind::Json(path) | SinkKind::Sqlite(path) => paths.push(path.as_str()),
+            SinkKind::Stdout => {}
+        }
+    }
+    paths.push(config.topology_dot.as_str());
+    paths.push(config.topology_graphml.as_str());
+    paths.push(config.network_report.as_str());
+    paths.push(config.churn_csv.as_str());
+    paths.extend(config.metrics_file.as_deref());
+    paths.extend(config.history_db.as_deref());
+    paths.extend(config.log_file.as_deref());
+
+    paths
+}
+
+/// Check that `path` can be written without changing it. A file that does not exist yet is
+/// created and removed again.
+fn check_writable(path: &Path) -> Result<String, CLIError> {
+    if path.exists() {
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| CLIError::writer(path, e))?;
+        return Ok(String::from("writable"));
+    }
+
+    File::create(path)
+        .and_then(|_| fs::remove_file(path))
+        .map_err(|e| CLIError::writer(path, e))?;
+    Ok(String::from("writable, will be created"))
+}
+
+fn check_listen(addr: SocketAddr) -> Result<String, CLIError> {
+    TcpListener::bind(addr)
+        .map(|_| String::from("free"))
+        .map_err(|e| CLIError::resource_busy(format!("cannot listen on {}", addr), e))
+}
+
+/// Dial every address once over the transport of a run, without running any protocol.
+async fn dial_bootstrap_nodes(
+    config: &Config,
+    addrs: Vec<Multiaddr>,
+    dial_timeout: Duration,
+) -> Vec<(String, Result<String, CLIError>)> {
+    let unreachable = |addr: &Multiaddr, reason: String| CLIError::BootstrapUnreachable {
+        addr: addr.to_string(),
+        reason,
+    };
+    let mut results: Vec<Option<Result<String, CLIError>>> = addrs.iter().map(|_| None).collect();
+
+    let transport = match Controller::build_transport_layer(config) {
+        Ok(transport) => transport,
+        Err(e) => {
+            let reason = e.to_string();
+            return addrs
+                .iter()
+                .map(|addr| (addr.to_string(), Err(unreachable(addr, reason.clone()))))
+                .collect();
+        }
+    };
+    let mut swarm = Swarm::new(
+        transport,
+        dummy::Behaviour,
+        config.keypair.public().to_peer_id(),
+        libp2p::swarm::Config::with_tokio_executor(),
+    );
+
+    // Stores (Connection ID -> Index of the dialed address)
+    let mut pending: HashMap<ConnectionId, usize> = HashMap::new();
+    for (index, addr) in addrs.iter().enumerate() {
+        let opts = DialOpts::unknown_peer_id().address(addr.clone()).build();
+        let connection_id = opts.connection_id();
+        match swarm.dial(opts) {
+            Ok(()) => {
+                pending.insert(connection_id, index);
+            }
+            Err(e) => results[index] = Some(Err(unreachable(addr, e.to_string()))),
+        }
+    }
+
+    let deadline = time::sleep(dial_timeout);
+    tokio::pin!(deadline);
+    while !pending.is_empty() {
+        tokio::select! {
+            event = swarm.select_next_some() => match event {
+                SwarmEvent::ConnectionEstablished { peer_id, connection_id, .. } => {
+                    if let Some(index) = pending.remove(&connection_id) {
+                        results[index] = Some(Ok(format!("connected to {}", peer_id)));
+                    }
+                }
+                SwarmEvent::OutgoingConnectionError { connection_id, error, .. } => {
+                    if let Some(index) = pending.remove(&connection_id) {
+                        results[index] = Some(Err(unreachable(&addrs[index], error.to_string())));
+                    }
+                }
+                _ => {}
+            },
+            _ = &mut deadline => break,
+        }
+    }
+
+    // Dials still pending at the deadline count as unreachable
+    addrs
+        .iter()
+        .zip(results)
+        .map(|(addr, result)| {
+            let result = result.unwrap_or_else(|| {
+                Err(unreachable(
+                    addr,
+                    format!("no answer within {}s", dial_timeout.as_secs()),
+                ))
+            });
+            (addr.to_string(), result)
+        })
+        .collect()
+}
+
+/// Time to wait for the bootstrap nodes to answer unless `check --dial-timeout` is given (in s)
+pub const DEFAULT_DIAL_TIMEOUT: u64 = 5;
+
+const BOOTSTRAP_ADDRS: &str = "Bootstrap Addresses";
+const OUTPUT_PATHS: &str = "Output Paths";
+const LISTEN_ADDRS: &str = "Listen Addresses";
+const BOOTSTRAP_DIALS: &str = "Bootstrap Dials";
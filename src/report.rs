@@ -0,0 +1,76 @@
+//! Network report module to write human readable report sections to a file.
+
+use serde::Serialize;
+use std::{fs::File, io::Write, path::PathBuf};
+
+use crate::error::CLIError;
+
+/// A titled list of (Metric, Value) rows.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReportSection {
+    pub title: String,
+    pub rows: Vec<(String, String)>,
+}
+
+impl ReportSection {
+    pub fn new(title: &str) -> Self {
+        ReportSection {
+            title: title.to_string(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Append a (Metric, Value) row to the section.
+    pub fn push<T: ToString>(&mut self, metric: &str, value: T) {
+        self.rows.push((metric.to_string(), value.to_string()));
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct NetworkReport {
+    pub sections: Vec<ReportSection>,
+}
+
+impl NetworkReport {
+    pub fn new() -> Self {
+        NetworkReport {
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, section: ReportSection) {
+        self.sections.push(section);
+    }
+
+    /// Render every section as plain text.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        for section in self.sections.iter() {
+            output.push_str(&format!("== {} ==\n", section.title));
+            let width = section
+                .rows
+                .iter()
+                .map(|(metric, _)| metric.len())
+                .max()
+                .unwrap_or(0);
+            for (metric, value) in section.rows.iter() {
+                output.push_str(&format!("{:width$} : {}\n", metric, value, width = width));
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    // `write_to_file` writes all sections to `path`
+    // when `config.operation_duration` has been covered.
+    pub(crate) fn write_to_file(&self, path: PathBuf) -> Result<(), CLIError> {
+        let mut file = File::create(&path).map_err(|e| CLIError::writer(&path, e))?;
+        file.write_all(self.render().as_bytes())
+            .map_err(|e| CLIError::writer(&path, e))?;
+        file.flush().map_err(|e| CLIError::writer(&path, e))?;
+
+        Ok(())
+    }
+}
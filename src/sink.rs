@@ -0,0 +1,480 @@
+//! Report sinks the peer tables and report sections are written to.
+//! Several sinks can be active in the same run, e.g. CSV files and a console summary.
+
+use chrono::{DateTime, Utc};
+use csv::{Reader, StringRecord, Writer};
+use rusqlite::{params, Connection};
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+use crate::{
+    config::{Config, CsvNaming, SinkKind},
+    error::CLIError,
+    report::NetworkReport,
+    state::ControllerState,
+    writer::{Headers, Status},
+};
+
+/// A destination for the results of a run.
+pub trait ReportSink: Send {
+    /// Where the results go, used in logs.
+    fn name(&self) -> String;
+
+    /// Write the peer tables and report sections as they stand.
+    fn write(&mut self, state: &ControllerState, report: &NetworkReport) -> Result<(), CLIError>;
+}
+
+/// Build a sink for every kind in `config.sinks`.
+pub fn from_config(config: &Config) -> Vec<Box<dyn ReportSink>> {
+    config
+        .sinks
+        .iter()
+        .map(|kind| -> Box<dyn ReportSink> {
+            match kind {
+                SinkKind::Csv => Box::new(CsvSink::new(config)),
+                SinkKind::Json(path) => Box::new(JsonSink::new(PathBuf::from(path))),
+                SinkKind::Sqlite(path) => Box::new(SqliteSink::new(PathBuf::from(path))),
+                SinkKind::Stdout => Box::new(StdoutSink),
+            }
+        })
+        .collect()
+}
+
+/// Newly connected and unreachable peers as two CSV files.
+pub struct CsvSink {
+    just_connected: PathBuf,
+    unreachable: PathBuf,
+    /// Append to existing files instead of replacing them
+    append: bool,
+    naming: CsvNaming,
+    /// Size a file is rotated at (in bytes)
+    max_size: Option<u64>,
+    /// Unix timestamp the sink was created at (in s), used for per-run file names
+    started_at: i64,
+    /// Stores (Written file -> Offset the rows of this run start at, 0 if this run created it),
+    /// so a flush replaces the rows it wrote earlier instead of writing them twice
+    run_offsets: BTreeMap<PathBuf, u64>,
+}
+
+impl CsvSink {
+    pub fn new(config: &Config) -> Self {
+        CsvSink {
+            just_connected: PathBuf::from(&config.just_connected),
+            unreachable: PathBuf::from(&config.unreachable_csv),
+            append: config.csv_append,
+            naming: config.csv_naming,
+            max_size: config.csv_max_size,
+            started_at: Utc::now().timestamp(),
+            run_offsets: BTreeMap::new(),
+        }
+    }
+
+    fn write_table(
+        &mut self,
+        template: &Path,
+        status: Status,
+        state: &ControllerState,
+    ) -> Result<(), CLIError> {
+        let path = self.file_name(template);
+        let (header, rows) = Self::table(&status, state);
+
+        // Start a new file once the current one is full. A file this run created only holds
+        // rows of this run, which are rewritten as a whole, so it is never rotated.
+        let offset = self.run_offset(&path);
+        if let Some(max_size) = self.max_size {
+            if offset != Some(0)
+                && fs::metadata(&path).is_ok_and(|metadata| metadata.len() >= max_size)
+            {
+                // The rows of this run move to the new file, so they are not kept twice
+                if let Some(offset) = offset {
+                    truncate(&path, offset)?;
+                }
+                rotate(&path)?;
+                self.run_offsets.remove(&path);
+            }
+        }
+
+        let file = match self.run_offset(&path) {
+            // Replace the rows written earlier in this run, e.g. their last-seen times changed
+            Some(0) => create_table(&path, &header)?,
+            Some(offset) => truncate(&path, offset)?,
+            None => self.open_table(&path, &header)?,
+        };
+
+        let mut writer = Writer::from_writer(file);
+        for row in rows {
+            writer
+                .write_record(&row)
+                .map_err(|e| CLIError::writer(&path, e))?;
+        }
+
+        writer.flush().map_err(|e| CLIError::writer(&path, e))?;
+
+        Ok(())
+    }
+
+    /// Open the file at `path` for the first write of this run, with the header written.
+    /// In append mode the rows of earlier runs are kept. Either way the offset the rows of this
+    /// run start at is remembered.
+    fn open_table(&mut self, path: &Path, header: &[String]) -> Result<File, CLIError> {
+        // Only append to files with the same columns, e.g. not to files of an older version
+        let append = self.append
+            && match read_header(path) {
+                Some(existing) if existing.iter().eq(header.iter()) => true,
+                Some(_) => {
+                    rotate(path)?;
+                    false
+                }
+                None => false,
+            };
+
+        if !append {
+            self.run_offsets.insert(path.to_path_buf(), 0);
+            return create_table(path, header);
+        }
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| CLIError::writer(path, e))?;
+        let offset = file
+            .metadata()
+            .map_err(|e| CLIError::writer(path, e))?
+            .len();
+        self.run_offsets.insert(path.to_path_buf(), offset);
+
+        Ok(file)
+    }
+
+    /// The offset the rows of this run start at in `path`, if this run appended to it and the
+    /// file has not been moved or truncated since.
+    fn run_offset(&self, path: &Path) -> Option<u64> {
+        let offset = *self.run_offsets.get(path)?;
+        fs::metadata(path)
+            .is_ok_and(|metadata| metadata.len() >= offset)
+            .then_some(offset)
+    }
+
+    /// The header and rows of the table for `status`.
+    fn table(status: &Status, state: &ControllerState) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut header = vec![Headers::Peer.to_string()];
+
+        match status {
+            Status::Unreachable => {
+                header.push(Headers::Status.to_string());
+                header.push(Headers::FirstSeen.to_string());
+                header.push(Headers::LastSeen.to_string());
+
+                let rows = state
+                    .unreachable
+                    .iter()
+                    .map(|peer| {
+                        vec![
+                            peer.address.clone(),
+                            peer.status.clone(),
+                            peer.first_seen.clone(),
+                            peer.last_seen.clone(),
+                        ]
+                    })
+                    .collect();
+
+                (header, rows)
+            }
+            Status::NewlyConnected => {
+                header.push(Headers::IpAddr.to_string());
+                header.push(Headers::FirstSeen.to_string());
+                header.push(Headers::LastSeen.to_string());
+                header.push(Headers::DiscoverySource.to_string());
+                header.push(Headers::DiscoveredAt.to_string());
+                header.push(Headers::DiscoveredBy.to_string());
+                header.push(Headers::AgentVersion.to_string());
+
+                let rows = state
+                    .peers
+                    .iter()
+                    .map(|peer| {
+                        vec![
+                            peer.peer_id.clone(),
+                            peer.ip.clone(),
+                            peer.first_seen.clone(),
+                            peer.last_seen.clone(),
+                            peer.discovery_source
+                                .clone()
+                                .unwrap_or_else(|| String::from("Unknown")),
+                            peer.discovered_at.clone().unwrap_or_default(),
+                            peer.discovered_by.clone().unwrap_or_default(),
+                            peer.agent_version.clone().unwrap_or_default(),
+                        ]
+                    })
+                    .collect();
+
+                (header, rows)
+            }
+        }
+    }
+
+    /// The file to write `template` to under the naming scheme of this sink.
+    fn file_name(&self, template: &Path) -> PathBuf {
+        let timestamp = match self.naming {
+            CsvNaming::Plain => return template.to_path_buf(),
+            CsvNaming::PerRun => self.started_at,
+            CsvNaming::Interval(interval) => {
+                let now = Utc::now().timestamp();
+                now - now % interval.max(1) as i64
+            }
+        };
+        let timestamp = DateTime::from_timestamp(timestamp, 0)
+            .unwrap_or_default()
+            .format(FILE_NAME_TIMESTAMP);
+
+        with_suffix(template, &format!("-{}", timestamp))
+    }
+}
+
+impl ReportSink for CsvSink {
+    fn name(&self) -> String {
+        format!(
+            "CSV ({}, {})",
+            self.file_name(&self.just_connected).display(),
+            self.file_name(&self.unreachable).display()
+        )
+    }
+
+    fn write(&mut self, state: &ControllerState, _: &NetworkReport) -> Result<(), CLIError> {
+        let (just_connected, unreachable) = (self.just_connected.clone(), self.unreachable.clone());
+        self.write_table(&just_connected, Status::NewlyConnected, state)?;
+        self.write_table(&unreachable, Status::Unreachable, state)
+    }
+}
+
+/// The header row of the CSV file at `path`, if it exists and is not empty.
+fn read_header(path: &Path) -> Option<StringRecord> {
+    Reader::from_path(path)
+        .ok()?
+        .headers()
+        .ok()
+        .filter(|header| !header.is_empty())
+        .cloned()
+}
+
+/// Move a full or outdated file at `path` out of the way, to the first free
+/// `<name>.<N>.<extension>`.
+/// Create the file at `path` with only `header` in it.
+fn create_table(path: &Path, header: &[String]) -> Result<File, CLIError> {
+    let mut file = File::create(path).map_err(|e| CLIError::writer(path, e))?;
+    let mut writer = Writer::from_writer(&mut file);
+    writer
+        .write_record(header)
+        .and_then(|_| writer.flush().map_err(Into::into))
+        .map_err(|e| CLIError::writer(path, e))?;
+    drop(writer);
+
+    Ok(file)
+}
+
+/// Cut the file at `path` back to `len` bytes, open for writing at its end.
+fn truncate(path: &Path, len: u64) -> Result<File, CLIError> {
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|mut file| {
+            file.set_len(len)?;
+            file.seek(SeekFrom::End(0))?;
+            Ok(file)
+        })
+        .map_err(|e| CLIError::writer(path, e))
+}
+
+fn rotate(path: &Path) -> Result<(), CLIError> {
+    let rotated = (1..)
+        .map(|n| with_suffix(path, &format!(".{}", n)))
+        .find(|rotated| !rotated.exists())
+        .unwrap_or_else(|| path.to_path_buf());
+    info!(
+        path = %path.display(),
+        rotated = %rotated.display(),
+        "Rotating CSV report"
+    );
+
+    fs::rename(path, &rotated).map_err(|e| CLIError::writer(path, e))
+}
+
+/// Insert `suffix` between the file stem and the extension of `path`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}{}", stem, suffix),
+    };
+
+    path.with_file_name(file_name)
+}
+
+/// The run status, peer tables, routing table and report sections as one JSON document.
+pub struct JsonSink {
+    path: PathBuf,
+}
+
+impl JsonSink {
+    pub fn new(path: PathBuf) -> Self {
+        JsonSink { path }
+    }
+}
+
+impl ReportSink for JsonSink {
+    fn name(&self) -> String {
+        format!("JSON ({})", self.path.display())
+    }
+
+    fn write(&mut self, state: &ControllerState, report: &NetworkReport) -> Result<(), CLIError> {
+        let mut file = File::create(&self.path).map_err(|e| CLIError::writer(&self.path, e))?;
+        let document = json!({
+            "status": state.status,
+            "peers": state.peers,
+            "unreachable": state.unreachable,
+            "routing_table": state.routing_table,
+            "report": report.sections,
+        });
+        serde_json::to_writer_pretty(&mut file, &document)
+            .map_err(|e| CLIError::writer(&self.path, e))?;
+        file.flush().map_err(|e| CLIError::writer(&self.path, e))?;
+
+        Ok(())
+    }
+}
+
+/// Peer tables and report sections as SQLite tables, recreated on every write.
+pub struct SqliteSink {
+    path: PathBuf,
+}
+
+impl SqliteSink {
+    pub fn new(path: PathBuf) -> Self {
+        SqliteSink { path }
+    }
+
+    fn write_tables(
+        &self,
+        state: &ControllerState,
+        report: &NetworkReport,
+    ) -> Result<(), rusqlite::Error> {
+        let mut connection = Connection::open(&self.path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(SQLITE_SCHEMA)?;
+        for peer in state.peers.iter() {
+            transaction.execute(
+                "INSERT INTO peers VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    peer.peer_id,
+                    peer.ip,
+                    peer.first_seen,
+                    peer.last_seen,
+                    peer.discovery_source,
+                    peer.discovered_at,
+                    peer.discovered_by,
+                    peer.agent_version
+                ],
+            )?;
+        }
+        for peer in state.unreachable.iter() {
+            transaction.execute(
+                "INSERT INTO unreachable VALUES (?1, ?2, ?3, ?4)",
+                params![peer.address, peer.status, peer.first_seen, peer.last_seen],
+            )?;
+        }
+        for section in report.sections.iter() {
+            for (metric, value) in section.rows.iter() {
+                transaction.execute(
+                    "INSERT INTO report VALUES (?1, ?2, ?3)",
+                    params![section.title, metric, value],
+                )?;
+            }
+        }
+
+        transaction.commit()
+    }
+}
+
+impl ReportSink for SqliteSink {
+    fn name(&self) -> String {
+        format!("SQLite ({})", self.path.display())
+    }
+
+    fn write(&mut self, state: &ControllerState, report: &NetworkReport) -> Result<(), CLIError> {
+        self.write_tables(state, report)
+            .map_err(|e| CLIError::writer(&self.path, e))
+    }
+}
+
+/// A summary of the run and every report section on the console.
+pub struct StdoutSink;
+
+impl ReportSink for StdoutSink {
+    fn name(&self) -> String {
+        String::from("stdout")
+    }
+
+    fn write(&mut self, state: &ControllerState, report: &NetworkReport) -> Result<(), CLIError> {
+        let status = &state.status;
+        let mut stdout = std::io::stdout().lock();
+        let summary = format!(
+            "== Run Summary ==\n\
+             Local PeerID       : {}\n\
+             Mode               : {}\n\
+             Uptime (s)         : {}\n\
+             Discovered peers   : {}\n\
+             Identified peers   : {}\n\
+             Connected peers    : {}\n\
+             Unreachable addrs  : {}\n\n{}",
+            status.local_peer_id,
+            status.mode,
+            status.uptime,
+            status.discovered_peers,
+            state.peers.len(),
+            status.connected_peers,
+            status.unreachable_addrs,
+            report.render()
+        );
+        stdout
+            .write_all(summary.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|e| CLIError::writer(Path::new("stdout"), e))
+    }
+}
+
+// Timestamp in per-run and per-interval CSV file names
+const FILE_NAME_TIMESTAMP: &str = "%Y%m%dT%H%M%SZ";
+
+// Tables hold the latest write only, so they are recreated to pick up schema changes
+const SQLITE_SCHEMA: &str = "
+    DROP TABLE IF EXISTS peers;
+    DROP TABLE IF EXISTS unreachable;
+    DROP TABLE IF EXISTS report;
+    CREATE TABLE peers (
+        peer_id TEXT PRIMARY KEY,
+        ip TEXT NOT NULL,
+        first_seen TEXT NOT NULL,
+        last_seen TEXT NOT NULL,
+        discovery_source TEXT,
+        discovered_at TEXT,
+        discovered_by TEXT,
+        agent_version TEXT
+    );
+    CREATE TABLE unreachable (
+        address TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        first_seen TEXT NOT NULL,
+        last_seen TEXT NOT NULL
+    );
+    CREATE TABLE report (
+        section TEXT NOT NULL,
+        metric TEXT NOT NULL,
+        value TEXT NOT NULL
+    );
+";
@@ -0,0 +1,63 @@
+//! Live state of a running controller, as served by the HTTP API.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ControllerState {
+    pub status: RunStatus,
+    pub peers: Vec<PeerState>,
+    pub unreachable: Vec<UnreachableState>,
+    pub routing_table: Vec<RoutingEntry>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunStatus {
+    pub local_peer_id: String,
+    pub mode: String,
+    /// Time the controller has been running (in s)
+    pub uptime: u64,
+    /// Time left until `config.operation_duration` is covered (in s)
+    pub remaining: u64,
+    pub connected_peers: usize,
+    pub discovered_peers: usize,
+    pub unreachable_addrs: usize,
+    pub pending_queries: usize,
+}
+
+/// A newly connected peer and how it was discovered.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PeerState {
+    pub peer_id: String,
+    pub ip: String,
+    /// Time the peer was first identified at, in `config.timestamp_format`.
+    /// Reports of older versions call this `timestamp`.
+    #[serde(alias = "timestamp")]
+    pub first_seen: String,
+    /// Time the peer was last identified or connected at, in `config.timestamp_format`
+    #[serde(default)]
+    pub last_seen: String,
+    pub discovery_source: Option<String>,
+    /// Time the peer was first discovered at, in `config.timestamp_format`
+    pub discovered_at: Option<String>,
+    pub discovered_by: Option<String>,
+    /// Agent version last reported through identify
+    pub agent_version: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UnreachableState {
+    pub address: String,
+    pub status: String,
+    /// Time a dial to the address first failed at, in `config.timestamp_format`
+    #[serde(alias = "timestamp")]
+    pub first_seen: String,
+    /// Time a dial to the address last failed at, in `config.timestamp_format`
+    #[serde(default)]
+    pub last_seen: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RoutingEntry {
+    pub peer_id: String,
+    pub addresses: Vec<String>,
+}
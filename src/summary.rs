@@ -0,0 +1,95 @@
+//! Summary of a run, printed on the console once the run is over.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::report::{NetworkReport, ReportSection};
+
+/// The numbers that tell at a glance whether the network looked healthy.
+#[derive(Clone, Debug, Default)]
+pub struct RunSummary {
+    /// Time the run took
+    pub elapsed: Duration,
+    pub discovered_peers: usize,
+    pub identified_peers: usize,
+    /// Peers with at least one open connection when the run ended
+    pub connected_peers: usize,
+    pub unreachable_addrs: usize,
+    /// Stores (Dial error class -> Number of failed dials)
+    pub dial_failures: BTreeMap<String, usize>,
+    /// (Agent version, Number of peers) of the most common agent versions, most common first
+    pub agent_versions: Vec<(String, usize)>,
+    pub bootstrap_nodes: usize,
+    pub bootstrap_connected: usize,
+    /// Number of islands the discovered overlay falls apart into
+    pub islands: usize,
+}
+
+impl RunSummary {
+    /// Everything that makes the network look unhealthy, empty if nothing does.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.bootstrap_connected < self.bootstrap_nodes {
+            problems.push(format!(
+                "{} of {} bootstrap nodes unreachable",
+                self.bootstrap_nodes - self.bootstrap_connected,
+                self.bootstrap_nodes
+            ));
+        }
+        if self.identified_peers == 0 {
+            problems.push(String::from("No peers identified"));
+        }
+        if self.islands > 1 {
+            problems.push(format!("Overlay partitioned into {} islands", self.islands));
+        }
+
+        problems
+    }
+
+    /// The summary table followed by failed dials and agent versions, if there were any.
+    pub fn to_report(&self) -> NetworkReport {
+        let mut report = NetworkReport::new();
+        let problems = self.problems();
+
+        let mut summary = ReportSection::new("Run Summary");
+        summary.push(
+            "Health",
+            if problems.is_empty() {
+                "Healthy"
+            } else {
+                "Degraded"
+            },
+        );
+        for problem in problems.iter() {
+            summary.push("  Problem", problem);
+        }
+        summary.push("Elapsed (s)", self.elapsed.as_secs());
+        summary.push("Discovered peers", self.discovered_peers);
+        summary.push("Identified peers", self.identified_peers);
+        summary.push("Connected peers", self.connected_peers);
+        summary.push("Unreachable addrs", self.unreachable_addrs);
+        summary.push(
+            "Bootstrap nodes (connected)",
+            format!("{}/{}", self.bootstrap_connected, self.bootstrap_nodes),
+        );
+        report.push(summary);
+
+        if !self.dial_failures.is_empty() {
+            let mut section = ReportSection::new("Failed Dials");
+            for (error, count) in self.dial_failures.iter() {
+                section.push(error, count);
+            }
+            report.push(section);
+        }
+
+        if !self.agent_versions.is_empty() {
+            let mut section = ReportSection::new("Top Agent Versions");
+            for (agent_version, peers) in self.agent_versions.iter() {
+                section.push(agent_version, peers);
+            }
+            report.push(section);
+        }
+
+        report
+    }
+}
@@ -0,0 +1,122 @@
+//! Network topology module to track which peer reported which neighbours
+//! and export the resulting overlay as a graph.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::Write,
+    path::PathBuf,
+};
+
+use crate::error::CLIError;
+
+#[derive(Clone, Default)]
+pub struct NetworkTopology {
+    /// Stores (Reporting PeerID -> Reported PeerIDs)
+    pub edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl NetworkTopology {
+    pub fn new() -> Self {
+        NetworkTopology {
+            edges: BTreeMap::new(),
+        }
+    }
+
+    /// Record a directed edge "`source` reported `target`".
+    pub fn add_edge(&mut self, source: String, target: String) {
+        if source == target {
+            return;
+        }
+
+        self.edges.entry(source).or_default().insert(target);
+    }
+
+    /// Every peer in the graph, both reporting and reported.
+    pub fn nodes(&self) -> BTreeSet<&String> {
+        self.edges
+            .iter()
+            .flat_map(|(source, targets)| std::iter::once(source).chain(targets.iter()))
+            .collect()
+    }
+
+    /// Number of directed edges in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().map(|targets| targets.len()).sum()
+    }
+
+    /// Render the graph in GraphViz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph network {\n");
+
+        for node in self.nodes() {
+            dot.push_str(&format!("    \"{}\";\n", node));
+        }
+        for (source, targets) in self.edges.iter() {
+            for target in targets.iter() {
+                dot.push_str(&format!("    \"{}\" -> \"{}\";\n", source, target));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the graph in GraphML format.
+    pub fn to_graphml(&self) -> String {
+        let mut graphml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <graph id=\"network\" edgedefault=\"directed\">\n",
+        ));
+
+        for node in self.nodes() {
+            graphml.push_str(&format!("    <node id=\"{}\"/>\n", xml_escape(node)));
+        }
+        for (index, (source, target)) in self
+            .edges
+            .iter()
+            .flat_map(|(source, targets)| targets.iter().map(move |target| (source, target)))
+            .enumerate()
+        {
+            graphml.push_str(&format!(
+                "    <edge id=\"e{}\" source=\"{}\" target=\"{}\"/>\n",
+                index,
+                xml_escape(source),
+                xml_escape(target)
+            ));
+        }
+
+        graphml.push_str("  </graph>\n</graphml>\n");
+        graphml
+    }
+
+    // `write_to_file` writes the graph to `path` in the chosen format
+    // when `config.operation_duration` has been covered.
+    pub(crate) fn write_to_file(&self, path: PathBuf, format: GraphFormat) -> Result<(), CLIError> {
+        let contents = match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::GraphML => self.to_graphml(),
+        };
+
+        let mut file = File::create(&path).map_err(|e| CLIError::writer(&path, e))?;
+        file.write_all(contents.as_bytes())
+            .map_err(|e| CLIError::writer(&path, e))?;
+        file.flush().map_err(|e| CLIError::writer(&path, e))?;
+
+        Ok(())
+    }
+}
+
+pub(crate) enum GraphFormat {
+    Dot,
+    GraphML,
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,293 @@
+//! Live terminal dashboard shown while the controller runs.
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use futures::{channel::mpsc::UnboundedReceiver, StreamExt};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{self, Stdout},
+    path::Path,
+    time::Duration,
+};
+use tokio::time::{self, timeout};
+
+use crate::{
+    controller::Controller, error::CLIError, events::ControllerEvent, handle::ControllerHandle,
+    logging, state::ControllerState,
+};
+
+/// Dashboard with live peer counts, recent events, dial failures and a scrolling peer table.
+/// Log output is silenced while it is shown, as it would draw over the dashboard.
+pub struct Dashboard {
+    handle: ControllerHandle,
+    events: UnboundedReceiver<ControllerEvent>,
+    /// Peer caches and run status as of the last refresh
+    state: Option<ControllerState>,
+    /// Most recent events, newest first
+    recent: VecDeque<String>,
+    /// Stores (Dial error -> Number of failed dials)
+    dial_failures: BTreeMap<String, usize>,
+    /// First row of the peer table that is shown
+    offset: usize,
+}
+
+impl Dashboard {
+    /// Set up a dashboard for `controller`, to be run while the controller runs.
+    pub fn new(controller: &mut Controller) -> Self {
+        Dashboard {
+            handle: controller.handle(),
+            events: controller.events(),
+            state: None,
+            recent: VecDeque::new(),
+            dial_failures: BTreeMap::new(),
+            offset: 0,
+        }
+    }
+
+    /// Show the dashboard until the controller stops. Pressing q, Esc or Ctrl-C stops the controller.
+    pub async fn run(mut self) -> Result<(), CLIError> {
+        let mut terminal = enable_raw_mode()
+            .and_then(|_| execute!(io::stdout(), EnterAlternateScreen))
+            .and_then(|_| Terminal::new(CrosstermBackend::new(io::stdout())))
+            .map_err(|e| CLIError::resource_busy("cannot open the terminal dashboard", e))?;
+        let log_filter = logging::filter();
+        let _ = logging::set_filter("off");
+
+        let result = self.refresh_loop(&mut terminal).await;
+
+        if let Some(log_filter) = log_filter {
+            let _ = logging::set_filter(&log_filter);
+        }
+        let restored = disable_raw_mode()
+            .and_then(|_| execute!(terminal.backend_mut(), LeaveAlternateScreen))
+            .and_then(|_| terminal.show_cursor());
+
+        result.and(restored.map_err(|e| CLIError::writer(Path::new("terminal"), e)))
+    }
+
+    async fn refresh_loop(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    ) -> Result<(), CLIError> {
+        let mut refresh = time::interval(REFRESH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                event = self.events.next() => match event {
+                    Some(event) => self.on_event(event),
+                    // The event stream ends once the controller has stopped
+                    None => return Ok(()),
+                },
+                _ = refresh.tick() => {
+                    // A stopped controller does not answer, its event stream ends right after
+                    if let Ok(Some(state)) = timeout(REFRESH_INTERVAL, self.handle.state()).await {
+                        self.state = Some(state);
+                    }
+                    self.on_input()
+                        .and_then(|_| terminal.draw(|frame| self.draw(frame)).map(|_| ()))
+                        .map_err(|e| CLIError::writer(Path::new("terminal"), e))?;
+                }
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: ControllerEvent) {
+        let line = match event {
+            ControllerEvent::PeerDiscovered {
+                peer_id, source, ..
+            } => {
+                format!("Discovered {} ({})", peer_id, source.to_string())
+            }
+            ControllerEvent::PeerIdentified {
+                peer_id,
+                agent_version,
+                ..
+            } => format!("Identified {} ({})", peer_id, agent_version),
+            ControllerEvent::ConnectionEstablished { peer_id, .. } => {
+                format!("Connected {}", peer_id)
+            }
+            ControllerEvent::ConnectionClosed { peer_id, cause } => match cause {
+                Some(cause) => format!("Disconnected {} ({})", peer_id, cause),
+                None => format!("Disconnected {}", peer_id),
+            },
+            ControllerEvent::PeerUnreachable { address, error } => {
+                *self.dial_failures.entry(error.clone()).or_default() += 1;
+                format!("Unreachable {} ({})", address, error)
+            }
+            ControllerEvent::QueryFinished {
+                peers, timed_out, ..
+            } => format!(
+                "Lookup {} with {} peers",
+                if timed_out { "timed out" } else { "finished" },
+                peers.len()
+            ),
+        };
+
+        self.recent.push_front(line);
+        self.recent.truncate(RECENT_EVENTS);
+    }
+
+    /// Handle every pending key press without waiting for one.
+    fn on_input(&mut self) -> io::Result<()> {
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read()?
+            else {
+                continue;
+            };
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => self.handle.stop(),
+                // Raw mode swallows the interrupt signal
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.handle.stop()
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.scroll(1),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll(-1),
+                KeyCode::PageDown => self.scroll(PAGE as isize),
+                KeyCode::PageUp => self.scroll(-(PAGE as isize)),
+                KeyCode::Home => self.offset = 0,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn scroll(&mut self, rows: isize) {
+        let peers = self.state.as_ref().map_or(0, |state| state.peers.len());
+        self.offset = self
+            .offset
+            .saturating_add_signed(rows)
+            .min(peers.saturating_sub(1));
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [status, counts, activity, peers] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Percentage(40),
+            Constraint::Min(5),
+        ])
+        .areas(frame.size());
+        let [recent, failures] =
+            Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)])
+                .areas(activity);
+
+        let Some(state) = &self.state else {
+            frame.render_widget(
+                Paragraph::new("Waiting for the controller...").block(bordered("libp2p-test-tool")),
+                frame.size(),
+            );
+            return;
+        };
+        let run = &state.status;
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "{} | {} | up {}s | {}s left | q quit, up/down scroll",
+                run.local_peer_id, run.mode, run.uptime, run.remaining
+            ))
+            .block(bordered("libp2p-test-tool")),
+            status,
+        );
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Discovered {}   Identified {}   Connected {}   Unreachable {}   Pending queries {}",
+                run.discovered_peers,
+                state.peers.len(),
+                run.connected_peers,
+                run.unreachable_addrs,
+                run.pending_queries
+            ))
+            .block(bordered("Peers")),
+            counts,
+        );
+        frame.render_widget(
+            List::new(self.recent.iter().map(String::as_str)).block(bordered("Recent Events")),
+            recent,
+        );
+        frame.render_widget(
+            List::new(
+                self.dial_failures
+                    .iter()
+                    .map(|(error, count)| Line::from(format!("{:>5} {}", count, error))),
+            )
+            .block(bordered("Dial Failures")),
+            failures,
+        );
+        self.draw_peers(frame, state, peers);
+    }
+
+    fn draw_peers(&self, frame: &mut Frame, state: &ControllerState, area: Rect) {
+        let rows = state.peers.iter().skip(self.offset).map(|peer| {
+            Row::new([
+                peer.peer_id.clone(),
+                peer.ip.clone(),
+                peer.first_seen.clone(),
+                peer.last_seen.clone(),
+                peer.discovery_source.clone().unwrap_or_default(),
+                peer.agent_version.clone().unwrap_or_default(),
+            ])
+        });
+        let header = Row::new([
+            "PeerID",
+            "IP",
+            "First seen",
+            "Last seen",
+            "Source",
+            "Agent version",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let title = match self.offset {
+            0 => format!("Identified Peers ({})", state.peers.len()),
+            offset => format!(
+                "Identified Peers ({}, from row {})",
+                state.peers.len(),
+                offset + 1
+            ),
+        };
+
+        frame.render_widget(
+            Table::new(
+                rows,
+                [
+                    Constraint::Length(52),
+                    Constraint::Length(15),
+                    Constraint::Length(24),
+                    Constraint::Length(24),
+                    Constraint::Length(10),
+                    Constraint::Min(10),
+                ],
+            )
+            .header(header)
+            .block(bordered(&title)),
+            area,
+        );
+    }
+}
+
+fn bordered(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(title)
+}
+
+/// Interval the dashboard is redrawn at
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+/// Number of recent events kept on screen
+const RECENT_EVENTS: usize = 100;
+/// Number of rows a page up/down scrolls
+const PAGE: usize = 10;
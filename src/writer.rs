@@ -1,15 +1,51 @@
-//! CSV Writer module to write data to a CSV file.
+//! Peer caches filled during a run, written out through the report sinks.
 
-use csv::Writer;
-use std::{collections::BTreeMap, fs::File, path::PathBuf, sync::RwLock};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
 
-use crate::error::CLIError;
+use crate::{error::CLIError, report::ReportSection};
 
 pub struct CSVWriter {
-    /// Stores (PeerID)
-    pub newly_connected_peer_cache: RwLock<BTreeMap<String, (String, String)>>,
-    /// Stores (MultiAddress)
-    pub unreachable_peer_cache: RwLock<BTreeMap<String, (String, String)>>,
+    /// Stores (PeerID -> (IP, When the peer was identified))
+    pub newly_connected_peer_cache: RwLock<BTreeMap<String, (String, Seen)>>,
+    /// Stores (MultiAddress -> (Status, When dials to the address failed))
+    pub unreachable_peer_cache: RwLock<BTreeMap<String, (String, Seen)>>,
+    /// Stores (PeerID -> How the peer was first discovered)
+    pub discovered_peer_cache: RwLock<BTreeMap<String, Discovery>>,
+    /// Stores (PeerID -> Agent version last reported through identify)
+    pub agent_version_cache: RwLock<BTreeMap<String, String>>,
+}
+
+/// How and when a peer was first discovered.
+#[derive(Clone, Debug)]
+pub struct Discovery {
+    pub source: DiscoverySource,
+    pub timestamp: DateTime<Utc>,
+    /// PeerID of the peer that reported this peer, if any
+    pub discovered_by: Option<String>,
+}
+
+/// The first and last time something was seen during a run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Seen {
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+}
+
+impl Seen {
+    pub fn new(at: DateTime<Utc>) -> Self {
+        Seen {
+            first: at,
+            last: at,
+        }
+    }
+
+    pub fn update(&mut self, at: DateTime<Utc>) {
+        self.last = self.last.max(at);
+    }
 }
 
 impl CSVWriter {
@@ -17,54 +53,95 @@ impl CSVWriter {
         Ok(CSVWriter {
             newly_connected_peer_cache: RwLock::new(BTreeMap::new()),
             unreachable_peer_cache: RwLock::new(BTreeMap::new()),
+            discovered_peer_cache: RwLock::new(BTreeMap::new()),
+            agent_version_cache: RwLock::new(BTreeMap::new()),
         })
     }
 
-    // `append_data_to_csv` appends records to CSV when
-    // `config.operation_duration` has been covered.
-    pub(crate) async fn append_data_to_csv(
+    /// Record how a peer was discovered. Only the first discovery is kept,
+    /// returns whether this was the first discovery of `peer`.
+    pub fn record_discovery(
         &self,
-        path: PathBuf,
-        status: Status,
-    ) -> Result<(), CLIError> {
-        let file = File::create(&path).map_err(|_| CLIError::WriterError)?;
-        let mut writer = Writer::from_writer(file);
-        let mut record = vec![Headers::Peer.to_string()];
-
-        match status {
-            Status::Unreachable => {
-                record.push(Headers::Status.to_string());
-                record.push(Headers::Timestamp.to_string());
-
-                writer
-                    .write_record(record)
-                    .map_err(|_| CLIError::WriterError)?;
-
-                for (peer, stats) in self.unreachable_peer_cache.read().unwrap().iter() {
-                    writer
-                        .write_record(&[peer.clone(), stats.0.clone(), stats.1.clone()])
-                        .map_err(|_| CLIError::WriterError)?;
-                }
-            }
-            Status::NewlyConnected => {
-                record.push(Headers::IpAddr.to_string());
-                record.push(Headers::Timestamp.to_string());
-
-                writer
-                    .write_record(record)
-                    .map_err(|_| CLIError::WriterError)?;
-
-                for (peer, stats) in self.newly_connected_peer_cache.read().unwrap().iter() {
-                    writer
-                        .write_record(&[peer.clone(), stats.0.clone(), stats.1.clone()])
-                        .map_err(|_| CLIError::WriterError)?;
-                }
+        peer: String,
+        source: DiscoverySource,
+        discovered_by: Option<String>,
+        timestamp: DateTime<Utc>,
+    ) -> bool {
+        match write_cache(&self.discovered_peer_cache).entry(peer) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(Discovery {
+                    source,
+                    timestamp,
+                    discovered_by,
+                });
+                true
             }
-        };
+        }
+    }
 
-        writer.flush().map_err(|_| CLIError::WriterError)?;
+    /// Record `peer` as identified with `ip` at `at`. The first IP is kept.
+    pub fn record_connected(&self, peer: String, ip: String, at: DateTime<Utc>) {
+        write_cache(&self.newly_connected_peer_cache)
+            .entry(peer)
+            .and_modify(|(_, seen)| seen.update(at))
+            .or_insert((ip, Seen::new(at)));
+    }
+
+    /// Record a failed dial to `address` at `at`.
+    pub fn record_unreachable(&self, address: String, status: String, at: DateTime<Utc>) {
+        write_cache(&self.unreachable_peer_cache)
+            .entry(address)
+            .and_modify(|(_, seen)| seen.update(at))
+            .or_insert((status, Seen::new(at)));
+    }
 
-        Ok(())
+    /// Update when an identified `peer` was last seen, e.g. on a new connection.
+    pub fn record_seen(&self, peer: &str, at: DateTime<Utc>) {
+        if let Some((_, seen)) = write_cache(&self.newly_connected_peer_cache).get_mut(peer) {
+            seen.update(at);
+        }
+    }
+
+    /// Summarise discovery sources as a report section.
+    pub fn discovery_report_section(&self) -> ReportSection {
+        let mut section = ReportSection::new("Discovery Sources");
+        let discovered = read_cache(&self.discovered_peer_cache);
+        let connected = read_cache(&self.newly_connected_peer_cache);
+
+        for source in [
+            DiscoverySource::Bootstrap,
+            DiscoverySource::Mdns,
+            DiscoverySource::Kademlia,
+            DiscoverySource::Inbound,
+            DiscoverySource::Dialed,
+        ] {
+            let (all, identified) = discovered
+                .iter()
+                .filter(|(_, discovery)| discovery.source == source)
+                .fold((0, 0), |(all, identified), (peer, _)| {
+                    (all + 1, identified + connected.contains_key(peer) as usize)
+                });
+            section.push(
+                &format!("{} (discovered/identified)", source.to_string()),
+                format!("{}/{}", all, identified),
+            );
+        }
+
+        // Peers reported by a bootstrap node show whether bootstrapping works on its own
+        let via_bootstrap = discovered
+            .values()
+            .filter(|discovery| {
+                discovery
+                    .discovered_by
+                    .as_ref()
+                    .and_then(|by| discovered.get(by))
+                    .is_some_and(|by| by.source == DiscoverySource::Bootstrap)
+            })
+            .count();
+        section.push("Reported by bootstrap nodes", via_bootstrap);
+
+        section
     }
 }
 
@@ -73,6 +150,14 @@ pub(crate) enum Headers {
     IpAddr,
     Status,
     Timestamp,
+    FirstSeen,
+    LastSeen,
+    Event,
+    Source,
+    DiscoverySource,
+    DiscoveredAt,
+    DiscoveredBy,
+    AgentVersion,
 }
 
 impl ToString for Headers {
@@ -82,6 +167,14 @@ impl ToString for Headers {
             Headers::IpAddr => String::from("IpAddr"),
             Headers::Status => String::from("Status"),
             Headers::Timestamp => String::from("Timestamp"),
+            Headers::FirstSeen => String::from("FirstSeen"),
+            Headers::LastSeen => String::from("LastSeen"),
+            Headers::Event => String::from("Event"),
+            Headers::Source => String::from("Source"),
+            Headers::DiscoverySource => String::from("DiscoverySource"),
+            Headers::DiscoveredAt => String::from("DiscoveredAt"),
+            Headers::DiscoveredBy => String::from("DiscoveredBy"),
+            Headers::AgentVersion => String::from("AgentVersion"),
         }
     }
 }
@@ -99,3 +192,38 @@ impl ToString for Status {
         }
     }
 }
+
+/// The mechanism through which a peer was first discovered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiscoverySource {
+    Bootstrap,
+    Mdns,
+    Kademlia,
+    Inbound,
+    /// An outbound connection to a peer none of the other sources reported, e.g. a bootstrap
+    /// node reached through an address other than the configured one
+    Dialed,
+}
+
+impl ToString for DiscoverySource {
+    fn to_string(&self) -> String {
+        match self {
+            DiscoverySource::Bootstrap => String::from("Bootstrap"),
+            DiscoverySource::Mdns => String::from("Mdns"),
+            DiscoverySource::Kademlia => String::from("Kademlia"),
+            DiscoverySource::Inbound => String::from("Inbound"),
+            DiscoverySource::Dialed => String::from("Dialed"),
+        }
+    }
+}
+
+/// Lock a cache for reading. A poisoned lock only means a writer panicked,
+/// the cached peers are still worth reporting.
+pub(crate) fn read_cache<T>(cache: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    cache.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Lock a cache for writing, recovering the data from a poisoned lock.
+pub(crate) fn write_cache<T>(cache: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    cache.write().unwrap_or_else(PoisonError::into_inner)
+}
@@ -1,14 +1,45 @@
 //! Basic tests to demonstrate results of Task 1, 2 and 3
 //! with the help of this tool.
 
+use chrono::{DateTime, Utc};
 use csv::{Reader, StringRecord};
+use futures::StreamExt;
+use libp2p::{identity::Keypair, PeerId};
+use libp2p_test_tool::{
+    analysis::NetworkAnalysis,
+    bootstrap::{resolve_dnsaddr_with, validate_bootstrap_addr, validate_bootstrap_addrs},
+    churn::{ChurnSource, ChurnTracker},
+    diff::{PeerSnapshot, ReportDiff},
+    estimator::NetworkSizeEstimator,
+    logging,
+    preflight::Preflight,
+    report::NetworkReport,
+    sink::CsvSink,
+    state::{ControllerState, PeerState, RunStatus, UnreachableState},
+    summary::RunSummary,
+    topology::NetworkTopology,
+    writer::{CSVWriter, DiscoverySource},
+    CLIError, Config, Controller, ControllerEvent, CsvNaming, LogFormat, MdnsMode, Mode,
+    ReportSink, SinkKind, TimestampFormat,
+};
+use sha2::{Digest, Sha256};
 use std::{
+    collections::HashMap,
     env,
-    fs::File,
+    error::Error,
+    fs::{self, File},
+    io::{Read, Write},
+    net::TcpStream,
+    path::Path,
     process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
+use tracing::level_filters::LevelFilter;
 
 #[tokio::test(flavor = "multi_thread")]
 async fn test_unreachable_peer_log() {
@@ -120,6 +151,1125 @@ async fn test_new_peer_join_and_ip_resolution() {
     );
 }
 
+#[test]
+fn test_network_partition_analysis() {
+    let mut topology = NetworkTopology::new();
+
+    // A ring `b - c - f` hanging off `a` through `b`, and a separate island `d - e`
+    topology.add_edge(String::from("a"), String::from("b"));
+    topology.add_edge(String::from("b"), String::from("c"));
+    topology.add_edge(String::from("c"), String::from("f"));
+    topology.add_edge(String::from("f"), String::from("b"));
+    topology.add_edge(String::from("d"), String::from("e"));
+
+    let analysis = NetworkAnalysis::from_topology(&topology);
+
+    assert_eq!(analysis.is_partitioned(), true);
+    assert_eq!(analysis.components[1], vec!["d", "e"]);
+    assert_eq!(analysis.articulation_points, vec!["b"]);
+    assert_eq!(analysis.diameter_estimate, 2);
+}
+
+#[test]
+fn test_topology_export() {
+    let mut topology = NetworkTopology::new();
+
+    // Self edges are dropped and an edge reported twice is kept once
+    topology.add_edge(String::from("a"), String::from("b"));
+    topology.add_edge(String::from("a"), String::from("b"));
+    topology.add_edge(String::from("a"), String::from("a"));
+    topology.add_edge(String::from("b"), String::from("<c&d>"));
+
+    assert_eq!(topology.nodes().len(), 3);
+    assert_eq!(topology.edge_count(), 2);
+    assert_eq!(
+        topology.to_dot(),
+        "digraph network {\n    \"<c&d>\";\n    \"a\";\n    \"b\";\n    \"a\" -> \"b\";\n    \"b\" -> \"<c&d>\";\n}\n"
+    );
+
+    let graphml = topology.to_graphml();
+    assert!(graphml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    assert!(graphml.contains("<graph id=\"network\" edgedefault=\"directed\">"));
+    assert!(graphml.contains("    <node id=\"&lt;c&amp;d&gt;\"/>\n"));
+    assert!(graphml.contains("    <edge id=\"e0\" source=\"a\" target=\"b\"/>\n"));
+    assert!(graphml.contains("    <edge id=\"e1\" source=\"b\" target=\"&lt;c&amp;d&gt;\"/>\n"));
+    assert!(graphml.ends_with("  </graph>\n</graphml>\n"));
+}
+
+#[test]
+fn test_discovery_sources() {
+    let writer = CSVWriter::new().unwrap();
+    let now = Utc::now();
+    let discover = |peer: &str, source: DiscoverySource, by: Option<&str>| {
+        writer.record_discovery(peer.to_string(), source, by.map(String::from), now)
+    };
+
+    assert!(discover("a", DiscoverySource::Bootstrap, None));
+    assert!(discover("b", DiscoverySource::Kademlia, Some("a")));
+    assert!(discover("c", DiscoverySource::Mdns, None));
+    assert!(discover("d", DiscoverySource::Inbound, None));
+    assert!(discover("e", DiscoverySource::Dialed, None));
+    assert!(discover("f", DiscoverySource::Kademlia, Some("c")));
+    // Only the first discovery of a peer counts
+    assert!(!discover("b", DiscoverySource::Dialed, None));
+    for peer in ["a", "b", "e"] {
+        writer.record_connected(peer.to_string(), String::from("172.16.3.2"), now);
+    }
+
+    let section = writer.discovery_report_section();
+    let rows: Vec<(&str, &str)> = section
+        .rows
+        .iter()
+        .map(|(metric, value)| (metric.as_str(), value.as_str()))
+        .collect();
+    assert_eq!(section.title, "Discovery Sources");
+    assert_eq!(
+        rows,
+        vec![
+            ("Bootstrap (discovered/identified)", "1/1"),
+            ("Mdns (discovered/identified)", "1/0"),
+            ("Kademlia (discovered/identified)", "2/1"),
+            ("Inbound (discovered/identified)", "1/0"),
+            ("Dialed (discovered/identified)", "1/1"),
+            ("Reported by bootstrap nodes", "1"),
+        ]
+    );
+}
+
+#[test]
+fn test_churn_tracking() {
+    let started = DateTime::from_timestamp_millis(1_699_781_400_000).unwrap();
+    let at = |secs: i64| started + chrono::Duration::seconds(secs);
+    let mut churn = ChurnTracker::new(started);
+
+    churn.record_join(String::from("a"), ChurnSource::Connection, at(1));
+    churn.record_join(String::from("b"), ChurnSource::Connection, at(2));
+    churn.record_leave(String::from("b"), ChurnSource::Connection, at(3));
+    churn.record_leave(String::from("a"), ChurnSource::Connection, at(4));
+    churn.record_join(String::from("c"), ChurnSource::Connection, at(5));
+    // Mdns records and leaves without a session do not change the sessions
+    churn.record_join(String::from("d"), ChurnSource::Mdns, at(6));
+    churn.record_leave(String::from("d"), ChurnSource::Mdns, at(7));
+    churn.record_leave(String::from("e"), ChurnSource::Connection, at(8));
+
+    assert_eq!(churn.session_lengths, vec![1000, 3000]);
+    let rows: Vec<(String, String)> = churn.to_report_section(at(60)).rows;
+    let row = |metric: &str| {
+        rows.iter()
+            .find(|(name, _)| name == metric)
+            .map(|(_, value)| value.as_str())
+    };
+    assert_eq!(row("Joins"), Some("3"));
+    assert_eq!(row("Leaves"), Some("3"));
+    assert_eq!(row("Mdns expiries"), Some("1"));
+    assert_eq!(row("Churn rate (events/min)"), Some("6.00"));
+    assert_eq!(row("Completed sessions"), Some("2"));
+    assert_eq!(row("Open sessions"), Some("1"));
+    assert_eq!(row("Mean session length (ms)"), Some("2000"));
+    assert_eq!(row("Shortest session (ms)"), Some("1000"));
+    assert_eq!(row("Longest session (ms)"), Some("3000"));
+
+    // Every event is written in the order it was observed
+    let path = env::temp_dir().join("test_churn_tracking.csv");
+    churn
+        .write_to_csv(path.clone(), TimestampFormat::EpochMillis)
+        .unwrap();
+    let mut reader = Reader::from_path(&path).unwrap();
+    assert_eq!(
+        reader.headers().unwrap(),
+        &StringRecord::from(vec!["PeerID/MultiAddr", "Event", "Source", "Timestamp"])
+    );
+    let records: Vec<StringRecord> = reader.records().map(|record| record.unwrap()).collect();
+    assert_eq!(records.len(), 8);
+    assert_eq!(
+        records[0],
+        StringRecord::from(vec!["a", "Joined", "Connection", "1699781401000"])
+    );
+    assert_eq!(
+        records[6],
+        StringRecord::from(vec!["d", "Left", "Mdns", "1699781407000"])
+    );
+}
+
+#[test]
+fn test_network_size_estimate() {
+    const NETWORK_SIZE: usize = 1000;
+    const CLOSEST_PEERS: usize = 20;
+
+    // Deterministic peers and lookup keys, so the estimate is the same on every run
+    let peers: Vec<PeerId> = (0..NETWORK_SIZE)
+        .map(|i| {
+            let mut secret = [0u8; 32];
+            secret[..8].copy_from_slice(&(i as u64).to_be_bytes());
+            Keypair::ed25519_from_bytes(secret)
+                .unwrap()
+                .public()
+                .to_peer_id()
+        })
+        .collect();
+    let keys: Vec<_> = peers
+        .iter()
+        .map(|peer| Sha256::digest(peer.to_bytes()))
+        .collect();
+
+    let mut estimator = NetworkSizeEstimator::new();
+    assert!(estimator.estimate().is_none());
+    for lookup in 0..50 {
+        let target = format!("lookup-{}", lookup).into_bytes();
+        let target_key = Sha256::digest(&target);
+        // The peers a lookup for `target` returns: the closest by XOR distance of their keys
+        let mut closest: Vec<(Vec<u8>, PeerId)> = keys
+            .iter()
+            .zip(peers.iter())
+            .map(|(key, peer)| {
+                let distance = key.iter().zip(target_key.iter()).map(|(a, b)| a ^ b);
+                (distance.collect(), *peer)
+            })
+            .collect();
+        closest.sort();
+        let closest: Vec<PeerId> = closest
+            .into_iter()
+            .take(CLOSEST_PEERS)
+            .map(|(_, peer)| peer)
+            .collect();
+        estimator.add_sample(&target, &closest);
+    }
+
+    let estimate = estimator.estimate().unwrap();
+    assert_eq!(estimate.samples, 50);
+    let size = NETWORK_SIZE as f64;
+    assert!((estimate.estimate - size).abs() < 0.15 * size);
+    assert!(estimate.lower_bound < size && size < estimate.upper_bound);
+    assert!(estimate.upper_bound - estimate.lower_bound < 0.25 * size);
+
+    // A single lookup gives no interval, which the report bounds by the peers observed
+    let mut single = NetworkSizeEstimator::new();
+    single.samples.push(size);
+    assert_eq!(single.estimate().unwrap().upper_bound, f64::INFINITY);
+    let rows = single.to_report_section(400).rows;
+    assert_eq!(
+        rows[0],
+        (String::from("Distinct peers observed"), String::from("400"))
+    );
+    assert_eq!(
+        rows[2],
+        (String::from("Estimated peers"), String::from("1000"))
+    );
+    assert_eq!(
+        rows[3],
+        (
+            String::from("95% confidence interval"),
+            String::from("400 - inf")
+        )
+    );
+}
+
+#[test]
+fn test_random_peer_generation() {
+    let peer = PeerId::random();
+    let origin = peer.to_bytes();
+    let prefix = |bytes: &[u8], bits: usize| -> Vec<bool> {
+        (0..bits)
+            .map(|bit| bytes[bit / 8] & (0x80 >> (bit % 8)) != 0)
+            .collect()
+    };
+
+    // Every reconstructed peer shares the first `target_cpl` bits of its digest with the origin
+    for target_cpl in 1..=15 {
+        let reconstructed = Controller::generate_random_peer(peer, target_cpl).unwrap();
+        // Skip the multihash code and length
+        let digest = &reconstructed.to_bytes()[2..];
+        assert_eq!(
+            prefix(digest, target_cpl as usize),
+            prefix(&origin, target_cpl as usize),
+            "target_cpl {}",
+            target_cpl
+        );
+    }
+}
+
+#[test]
+fn test_bootstrap_addr_validation() {
+    let reason = |addr: &str| match validate_bootstrap_addr(addr) {
+        Err(CLIError::InvalidBootstrapAddr { reason, .. }) => reason,
+        _ => String::new(),
+    };
+
+    assert!(validate_bootstrap_addr(DISCONNECTED_PEER_MULTIADDRESS).is_ok());
+    assert!(
+        reason("/ip4/172.16.3.3/udp/37000/quic-v1/p2p/12D3KooWInvalid").starts_with("bad peer id")
+    );
+    assert!(reason("/ip4/172.16.3.3/udp/37000/quic-v1/p2p/").starts_with("missing peer id"));
+    assert!(reason("/ip4/172.16.3.3/udp/37000").starts_with("missing transport"));
+    assert!(reason("/ip9/172.16.3.3/tcp/37000").starts_with("unknown protocol"));
+
+    // Invalid addresses are only skipped on request
+    let addrs = vec![
+        String::from("/ip4/172.16.3.3/tcp/37000"),
+        String::from("/ip4/172.16.3.3/udp/37000"),
+    ];
+    assert!(validate_bootstrap_addrs(&addrs, false).is_err());
+    assert_eq!(validate_bootstrap_addrs(&addrs, true).unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_dnsaddr_resolution() {
+    let (peer_a, peer_b, peer_c, peer_d) = (
+        PeerId::random(),
+        PeerId::random(),
+        PeerId::random(),
+        PeerId::random(),
+    );
+    let records: HashMap<String, Vec<String>> = HashMap::from([
+        (
+            String::from("_dnsaddr.bootstrap.example"),
+            vec![
+                format!("dnsaddr=/dnsaddr/a.example/p2p/{}", peer_a),
+                format!("dnsaddr=/dnsaddr/missing.example/p2p/{}", peer_b),
+                format!("dnsaddr=/ip4/172.16.3.3/tcp/37000/p2p/{}", peer_c),
+                String::from("other=ignored"),
+            ],
+        ),
+        // A shared record set, of which only the entries of `peer_a` belong to the parent entry
+        (
+            String::from("_dnsaddr.a.example"),
+            vec![
+                format!("dnsaddr=/ip4/172.16.3.4/tcp/37000/p2p/{}", peer_a),
+                format!("dnsaddr=/ip4/172.16.3.5/tcp/37000/p2p/{}", peer_d),
+            ],
+        ),
+    ]);
+    let lookup = |name: String| {
+        let entries = records.get(&name).cloned();
+        async move { entries.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, name)) }
+    };
+
+    // The nested entry that cannot be resolved is skipped
+    let mut addrs = resolve_dnsaddr_with("bootstrap.example", lookup)
+        .await
+        .unwrap();
+    addrs.sort();
+    assert_eq!(
+        addrs,
+        vec![
+            format!("/ip4/172.16.3.3/tcp/37000/p2p/{}", peer_c),
+            format!("/ip4/172.16.3.4/tcp/37000/p2p/{}", peer_a),
+        ]
+    );
+
+    // Only the domain itself failing to resolve is an error
+    let e = resolve_dnsaddr_with("missing.example", lookup)
+        .await
+        .unwrap_err();
+    assert_eq!(e.exit_code(), 2);
+}
+
+#[test]
+fn test_error_messages() {
+    let cause = || std::io::Error::new(std::io::ErrorKind::Other, "cause");
+
+    // Every error prints a hint matching its cause and keeps the cause as its source
+    let transport = CLIError::transport("cannot sign the libp2p-noise static keypair", cause());
+    assert_eq!(
+        transport.to_string(),
+        "Could not set up the libp2p transport. Please check the node keypair. \
+         (cannot sign the libp2p-noise static keypair)"
+    );
+    let dns_config = CLIError::dns_config(cause());
+    assert_eq!(
+        dns_config.to_string(),
+        "Could not read the system DNS configuration. Please check /etc/resolv.conf."
+    );
+    let peer_id = CLIError::peer_id("cannot wrap reconstructed peer id", cause());
+    assert_eq!(
+        peer_id.to_string(),
+        "Could not generate a peer ID close to a known peer. (cannot wrap reconstructed peer id)"
+    );
+    for e in [transport, dns_config, peer_id] {
+        assert!(!e.to_string().contains("MultiAddress"));
+        assert!(!e.to_string().contains("restart the client"));
+        assert_eq!(e.source().unwrap().to_string(), "cause");
+        assert_eq!(e.exit_code(), 3);
+    }
+
+    let identity = CLIError::identity("/ip4/1.2.3.4/foo", cause());
+    assert!(identity
+        .to_string()
+        .contains("check the input address format"));
+    assert_eq!(identity.exit_code(), 2);
+}
+
+#[test]
+fn test_log_format() {
+    assert_eq!("text".parse::<LogFormat>(), Ok(LogFormat::Text));
+    assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+    assert!("yaml".parse::<LogFormat>().is_err());
+
+    // Filters are validated even before a logger is installed
+    assert!(logging::set_filter("info,libp2p_kad=debug").is_ok());
+    let e = logging::set_filter("info,libp2p_kad=loud").unwrap_err();
+    assert_eq!(e.exit_code(), 2);
+    assert!(e.to_string().contains("`libp2p_kad=loud`"));
+    assert_eq!(logging::filter(), None);
+
+    // -q and -v step down and up from the default info level
+    assert_eq!(logging::verbosity_level(-3), LevelFilter::OFF);
+    assert_eq!(logging::verbosity_level(-1), LevelFilter::WARN);
+    assert_eq!(logging::verbosity_level(0), LevelFilter::INFO);
+    assert_eq!(logging::verbosity_level(1), LevelFilter::DEBUG);
+    assert_eq!(logging::verbosity_level(5), LevelFilter::TRACE);
+
+    // Per-module filters come last, so they take precedence
+    let mut config = Config::default();
+    config.log_level = String::from("warn");
+    config.log_filter = Some(String::from("libp2p_kad=debug"));
+    let directives = logging::directives(&config);
+    assert!(directives.starts_with("warn,"));
+    assert!(directives.ends_with(",libp2p_kad=debug"));
+}
+
+#[test]
+fn test_timestamp_format() {
+    assert_eq!(
+        "rfc3339".parse::<TimestampFormat>(),
+        Ok(TimestampFormat::Rfc3339)
+    );
+    assert_eq!(
+        "epoch-ms".parse::<TimestampFormat>(),
+        Ok(TimestampFormat::EpochMillis)
+    );
+    assert!("epoch".parse::<TimestampFormat>().is_err());
+
+    // Both formats are in UTC, RFC 3339 with milliseconds
+    let at = DateTime::from_timestamp_millis(1_699_781_400_123).unwrap();
+    assert_eq!(
+        TimestampFormat::Rfc3339.format(&at),
+        "2023-11-12T09:30:00.123Z"
+    );
+    assert_eq!(TimestampFormat::EpochMillis.format(&at), "1699781400123");
+}
+
+#[test]
+fn test_run_summary() {
+    let healthy = RunSummary {
+        elapsed: Duration::from_secs(60),
+        discovered_peers: 3,
+        identified_peers: 2,
+        connected_peers: 2,
+        bootstrap_nodes: 1,
+        bootstrap_connected: 1,
+        islands: 1,
+        ..Default::default()
+    };
+    assert!(healthy.problems().is_empty());
+    // Sections without entries are left out
+    assert_eq!(
+        healthy.to_report().render(),
+        "== Run Summary ==\n\
+         Health                      : Healthy\n\
+         Elapsed (s)                 : 60\n\
+         Discovered peers            : 3\n\
+         Identified peers            : 2\n\
+         Connected peers             : 2\n\
+         Unreachable addrs           : 0\n\
+         Bootstrap nodes (connected) : 1/1\n\n"
+    );
+
+    // A partitioned overlay, an unreachable bootstrap node and no identified peer each degrade the run
+    let degraded = RunSummary {
+        discovered_peers: 4,
+        identified_peers: 0,
+        unreachable_addrs: 2,
+        dial_failures: [(String::from("transport"), 2)].into_iter().collect(),
+        agent_versions: vec![(String::from("avail-light/1.7.0"), 3)],
+        bootstrap_nodes: 2,
+        bootstrap_connected: 1,
+        islands: 3,
+        ..Default::default()
+    };
+    assert_eq!(
+        degraded.problems(),
+        vec![
+            "1 of 2 bootstrap nodes unreachable",
+            "No peers identified",
+            "Overlay partitioned into 3 islands",
+        ]
+    );
+
+    let report = degraded.to_report();
+    let titles: Vec<&str> = report
+        .sections
+        .iter()
+        .map(|section| section.title.as_str())
+        .collect();
+    assert_eq!(
+        titles,
+        vec!["Run Summary", "Failed Dials", "Top Agent Versions"]
+    );
+    let summary = &report.sections[0].rows;
+    assert_eq!(
+        summary[0],
+        (String::from("Health"), String::from("Degraded"))
+    );
+    assert_eq!(
+        summary[1..4]
+            .iter()
+            .filter(|(metric, _)| metric == "  Problem")
+            .count(),
+        3
+    );
+    assert_eq!(
+        report.sections[1].rows,
+        vec![(String::from("transport"), String::from("2"))]
+    );
+    assert_eq!(
+        report.sections[2].rows,
+        vec![(String::from("avail-light/1.7.0"), String::from("3"))]
+    );
+}
+
+#[test]
+fn test_report_diff() {
+    let results_dir = env::temp_dir().join("test_report_diff");
+    fs::create_dir_all(&results_dir).unwrap();
+    let path = |file: &str| results_dir.join(file);
+
+    // The earlier run as CSVs, before agent versions were recorded for `c`
+    fs::write(
+        path("newly_connected.csv"),
+        "PeerID/MultiAddr,IpAddr,Timestamp,DiscoverySource,DiscoveredAt,DiscoveredBy,AgentVersion\n\
+         a,172.16.3.2,1,Bootstrap,1,,avail-light/1.7.0\n\
+         b,172.16.3.3,1,Kademlia,1,a,avail-light/1.7.0\n\
+         c,172.16.3.4,1,Kademlia,1,a,\n",
+    )
+    .unwrap();
+    fs::write(
+        path("unreachable.csv"),
+        "PeerID/MultiAddr,Status,Timestamp\n/ip4/172.16.3.9/tcp/37000,Unreachable,1\n",
+    )
+    .unwrap();
+    // The later run as a JSON report
+    fs::write(
+        path("report.json"),
+        r#"{
+            "peers": [
+                {"peer_id": "a", "ip": "172.16.3.2", "timestamp": "2", "agent_version": "avail-light/1.8.0"},
+                {"peer_id": "c", "ip": "172.16.3.7", "timestamp": "2", "agent_version": "avail-light/1.8.0"},
+                {"peer_id": "d", "ip": "172.16.3.5", "timestamp": "2"}
+            ],
+            "unreachable": [{"address": "/ip4/172.16.3.3/tcp/37000", "status": "Unreachable", "timestamp": "2"}]
+        }"#,
+    )
+    .unwrap();
+
+    let before =
+        PeerSnapshot::from_files(&[path("newly_connected.csv"), path("unreachable.csv")]).unwrap();
+    let after = PeerSnapshot::from_files(&[path("report.json")]).unwrap();
+    let diff = ReportDiff::between(&before, &after);
+
+    assert_eq!(
+        diff.added,
+        vec![(String::from("d"), String::from("172.16.3.5"))]
+    );
+    assert_eq!(
+        diff.removed,
+        vec![(String::from("b"), String::from("172.16.3.3"))]
+    );
+    assert_eq!(diff.newly_unreachable, vec!["/ip4/172.16.3.3/tcp/37000"]);
+    assert_eq!(diff.recovered, vec!["/ip4/172.16.3.9/tcp/37000"]);
+    assert_eq!(diff.ip_changed.len(), 1);
+    assert_eq!(diff.ip_changed[0].0, "c");
+    // `c` had no agent version before, so only `a` counts as a version change
+    assert_eq!(
+        diff.version_changed,
+        vec![(
+            String::from("a"),
+            String::from("avail-light/1.7.0"),
+            String::from("avail-light/1.8.0")
+        )]
+    );
+    assert_eq!(diff.to_report().sections[0].title, "Changes");
+
+    // Files that are not reports of this tool are rejected
+    fs::write(path("other.csv"), "Name,Value\nfoo,bar\n").unwrap();
+    assert!(matches!(
+        PeerSnapshot::from_files(&[path("other.csv")]),
+        Err(CLIError::ReportReadError { .. })
+    ));
+}
+
+#[test]
+fn test_csv_append_and_rotation() {
+    let results_dir = env::temp_dir().join("test_csv_append_and_rotation");
+    let _ = fs::remove_dir_all(&results_dir);
+    let mut config = local_config(&results_dir, 0);
+    config.csv_append = true;
+    let newly_connected = results_dir.join("newly_connected.csv");
+    let unreachable = results_dir.join("unreachable.csv");
+    let rows = |path: &Path| Reader::from_path(path).unwrap().records().count();
+
+    let mut state = single_peer_state();
+    let report = NetworkReport::new();
+
+    // A file of an older version with other columns is rotated instead of appended to
+    fs::write(
+        &unreachable,
+        "PeerID/MultiAddr,Timestamp\n/ip4/172.16.3.8/tcp/37000,1\n",
+    )
+    .unwrap();
+
+    // Two runs append to the same files, a flush does not repeat rows of its run
+    let mut first_run = CsvSink::new(&config);
+    first_run.write(&state, &report).unwrap();
+    first_run.write(&state, &report).unwrap();
+    state.peers[0].peer_id = String::from("b");
+    CsvSink::new(&config).write(&state, &report).unwrap();
+    assert_eq!(rows(&newly_connected), 2);
+    assert_eq!(rows(&unreachable), 2);
+    assert_eq!(rows(&results_dir.join("unreachable.1.csv")), 1);
+
+    // A full file is rotated to the next free name before writing
+    config.csv_max_size = Some(1);
+    CsvSink::new(&config).write(&state, &report).unwrap();
+    assert_eq!(rows(&newly_connected), 1);
+    assert_eq!(rows(&results_dir.join("unreachable.2.csv")), 2);
+
+    // Per-run names carry the start time of the run
+    config.csv_naming = CsvNaming::PerRun;
+    let sink = CsvSink::new(&config);
+    assert!(sink.name().contains("newly_connected-"));
+}
+
+#[test]
+fn test_csv_append_within_run() {
+    let results_dir = env::temp_dir().join("test_csv_append_within_run");
+    let _ = fs::remove_dir_all(&results_dir);
+    let mut config = local_config(&results_dir, 0);
+    config.csv_append = true;
+    let newly_connected = results_dir.join("newly_connected.csv");
+    let rows = |path: &Path| -> Vec<StringRecord> {
+        Reader::from_path(path)
+            .unwrap()
+            .records()
+            .map(Result::unwrap)
+            .collect()
+    };
+    let mut state = single_peer_state();
+    let report = NetworkReport::new();
+
+    // A flush replaces the rows of its run, so their last-seen times stay current
+    let mut sink = CsvSink::new(&config);
+    sink.write(&state, &report).unwrap();
+    state.peers[0].last_seen = String::from("2");
+    sink.write(&state, &report).unwrap();
+    let written = rows(&newly_connected);
+    assert_eq!(written.len(), 1);
+    assert_eq!(&written[0][3], "2");
+
+    // A file rotated for its size mid-run gets every peer of the run
+    config.csv_max_size = Some(1);
+    let mut sink = CsvSink::new(&config);
+    sink.write(&state, &report).unwrap();
+    sink.write(&state, &report).unwrap();
+    assert_eq!(rows(&newly_connected).len(), 1);
+    assert_eq!(rows(&results_dir.join("newly_connected.1.csv")).len(), 1);
+
+    // A file that fills up after the first flush of a run is rotated once, keeping only the rows
+    // of earlier runs, and the rows of the run move to the new file
+    let mut mid_run_config = local_config(&results_dir.join("mid_run"), 0);
+    mid_run_config.csv_append = true;
+    let mid_run = results_dir.join("mid_run/newly_connected.csv");
+    let mut mid_run_state = single_peer_state();
+    CsvSink::new(&mid_run_config)
+        .write(&mid_run_state, &report)
+        .unwrap();
+    mid_run_config.csv_max_size = Some(fs::metadata(&mid_run).unwrap().len() + 1);
+    let mut sink = CsvSink::new(&mid_run_config);
+    sink.write(&mid_run_state, &report).unwrap();
+    assert_eq!(rows(&mid_run).len(), 2);
+    let mut peer = mid_run_state.peers[0].clone();
+    peer.peer_id = String::from("b");
+    mid_run_state.peers.push(peer);
+    sink.write(&mid_run_state, &report).unwrap();
+    sink.write(&mid_run_state, &report).unwrap();
+    assert_eq!(rows(&mid_run).len(), 2);
+    assert_eq!(
+        rows(&results_dir.join("mid_run/newly_connected.1.csv")).len(),
+        1
+    );
+    assert!(!results_dir.join("mid_run/newly_connected.2.csv").exists());
+
+    // So does the file of the next interval
+    config.csv_max_size = None;
+    config.csv_naming = CsvNaming::Interval(1);
+    let mut sink = CsvSink::new(&config);
+    sink.write(&state, &report).unwrap();
+    std::thread::sleep(Duration::from_millis(1100));
+    state.peers[0].last_seen = String::from("3");
+    sink.write(&state, &report).unwrap();
+    let mut files: Vec<_> = fs::read_dir(&results_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("newly_connected-")
+        })
+        .collect();
+    files.sort();
+    assert_eq!(files.len(), 2);
+    assert_eq!(&rows(&files[0])[0][3], "2");
+    assert_eq!(&rows(&files[1])[0][3], "3");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_controller_event_stream() {
+    let results_dir = env::temp_dir().join("test_controller_event_stream");
+
+    // A controller that only listens, and one that bootstraps from it
+    let mut listener_config = local_config(&results_dir.join("listener"), LISTENER_PORT);
+    listener_config.mode = Mode::LanSurvey;
+    let listener_peer_id = listener_config.keypair.public().to_peer_id();
+    let mut listener = Controller::new(listener_config).await.unwrap();
+    let listener_handle = listener.handle();
+    let listener_task = tokio::spawn(async move { listener.run().await });
+
+    let mut dialer_config = local_config(&results_dir.join("dialer"), DIALER_PORT);
+    dialer_config.bootstrap_addr = vec![format!(
+        "/ip4/127.0.0.1/tcp/{}/p2p/{}",
+        LISTENER_PORT, listener_peer_id
+    )];
+    let mut dialer = Controller::new(dialer_config).await.unwrap();
+    let mut events = dialer.events();
+    let dialer_handle = dialer.handle();
+    let dialer_task = tokio::spawn(async move { dialer.run().await });
+
+    let identified = timeout(Duration::from_secs(SLEEP), async {
+        while let Some(event) = events.next().await {
+            if let ControllerEvent::PeerIdentified { peer_id, .. } = event {
+                if peer_id == listener_peer_id {
+                    return true;
+                }
+            }
+        }
+        false
+    })
+    .await
+    .unwrap_or(false);
+
+    dialer_handle.stop();
+    listener_handle.stop();
+
+    assert!(identified);
+    assert!(dialer_task.await.unwrap().is_ok());
+    assert!(listener_task.await.unwrap().is_ok());
+    // The stream ends once the controller has stopped and the results are written
+    while events.next().await.is_some() {}
+    assert!(results_dir.join("dialer/network_report.txt").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_controller_commands() {
+    let results_dir = env::temp_dir().join("test_controller_commands");
+
+    let mut listener_config = local_config(&results_dir.join("listener"), COMMAND_LISTENER_PORT);
+    listener_config.mode = Mode::LanSurvey;
+    let listener_peer_id = listener_config.keypair.public().to_peer_id();
+    let mut listener = Controller::new(listener_config).await.unwrap();
+    let listener_handle = listener.handle();
+    let listener_task = tokio::spawn(async move { listener.run().await });
+
+    // The dialer starts without bootstrap nodes and is pointed at the listener mid-run
+    let mut dialer_config = local_config(&results_dir.join("dialer"), COMMAND_DIALER_PORT);
+    dialer_config.bootstrap_addr = Vec::new();
+    let mut dialer = Controller::new(dialer_config).await.unwrap();
+    let mut events = dialer.events();
+    let dialer_handle = dialer.handle();
+    let dialer_task = tokio::spawn(async move { dialer.run().await });
+
+    assert!(dialer_handle.add_bootstrap("/ip9/127.0.0.1").is_err());
+    dialer_handle
+        .add_bootstrap(&format!(
+            "/ip4/127.0.0.1/tcp/{}/p2p/{}",
+            COMMAND_LISTENER_PORT, listener_peer_id
+        ))
+        .unwrap();
+
+    let identified = timeout(Duration::from_secs(SLEEP), async {
+        while let Some(event) = events.next().await {
+            if matches!(event, ControllerEvent::PeerIdentified { peer_id, .. } if peer_id == listener_peer_id)
+            {
+                return true;
+            }
+        }
+        false
+    })
+    .await
+    .unwrap_or(false);
+    assert!(identified);
+
+    // The snapshot reflects the bootstrap node added at runtime
+    let report = dialer_handle.snapshot().await.unwrap();
+    let bootstrap = &report.sections[0];
+    assert_eq!(bootstrap.title, "Bootstrap Nodes");
+    assert!(bootstrap
+        .rows
+        .contains(&(String::from("Connected"), String::from("1/1"))));
+
+    dialer_handle.stop();
+    listener_handle.stop();
+    assert!(dialer_task.await.unwrap().is_ok());
+    assert!(listener_task.await.unwrap().is_ok());
+    assert!(dialer_handle.snapshot().await.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_http_api() {
+    let results_dir = env::temp_dir().join("test_http_api");
+
+    let mut config = local_config(&results_dir, API_LISTENING_PORT);
+    config.mode = Mode::LanSurvey;
+    config.http_api = Some(format!("127.0.0.1:{}", API_PORT).parse().unwrap());
+    config.metrics_file = Some(results_dir.join("metrics.txt").display().to_string());
+    let peer_id = config.keypair.public().to_peer_id();
+    let mut controller = Controller::new(config).await.unwrap();
+    let handle = controller.handle();
+    let task = tokio::spawn(async move { controller.run().await });
+
+    // Give the controller a moment to bind the API
+    sleep(Duration::from_secs(1)).await;
+
+    let status = http_request("GET", "/status").await;
+    assert!(status.starts_with("HTTP/1.1 200"));
+    assert!(status.contains(&format!("\"local_peer_id\":\"{}\"", peer_id)));
+    assert!(status.contains("\"mode\":\"LanSurvey\""));
+
+    assert!(http_request("GET", "/peers").await.ends_with("[]"));
+    assert!(http_request("GET", "/report")
+        .await
+        .contains("\"title\":\"Bootstrap Nodes\""));
+
+    let metrics = http_request("GET", "/metrics").await;
+    assert!(metrics.contains("libp2p_test_tool_connected_peers 0"));
+    assert!(metrics.contains("libp2p_test_tool_dial_attempts_total 0"));
+    // libp2p's own metrics are served alongside, e.g. the listen address of the swarm
+    assert!(metrics.contains("libp2p_test_tool_libp2p_swarm_new_listen_addr_total"));
+
+    // A flush writes the results while the controller keeps running
+    assert!(http_request("POST", "/flush")
+        .await
+        .starts_with("HTTP/1.1 204"));
+    assert!(results_dir.join("network_report.txt").exists());
+    assert!(fs::read_to_string(results_dir.join("metrics.txt"))
+        .unwrap()
+        .contains("libp2p_test_tool_connected_peers 0"));
+    assert!(!task.is_finished());
+
+    handle.stop();
+    assert!(task.await.unwrap().is_ok());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_report_sinks() {
+    let results_dir = env::temp_dir().join("test_report_sinks");
+    let json_path = results_dir.join("report.json");
+    let sqlite_path = results_dir.join("report.db");
+
+    let mut config = local_config(&results_dir, SINKS_LISTENING_PORT);
+    config.mode = Mode::LanSurvey;
+    config.sinks = vec![
+        SinkKind::Csv,
+        SinkKind::Json(json_path.display().to_string()),
+        SinkKind::Sqlite(sqlite_path.display().to_string()),
+    ];
+    let peer_id = config.keypair.public().to_peer_id();
+    let mut controller = Controller::new(config).await.unwrap();
+    let writes = Arc::new(AtomicUsize::new(0));
+    controller.add_sink(Box::new(CountingSink(writes.clone())));
+    let handle = controller.handle();
+    let task = tokio::spawn(async move { controller.run().await });
+
+    handle.stop();
+    assert!(task.await.unwrap().is_ok());
+
+    // Every sink is written in the same run
+    let mut reader = Reader::from_path(results_dir.join("newly_connected.csv")).unwrap();
+    assert_eq!(&reader.headers().unwrap()[0], "PeerID/MultiAddr");
+
+    let json: serde_json::Value = serde_json::from_reader(File::open(&json_path).unwrap()).unwrap();
+    assert_eq!(json["status"]["local_peer_id"], peer_id.to_string());
+    assert_eq!(json["report"][0]["title"], "Bootstrap Nodes");
+
+    let connection = rusqlite::Connection::open(&sqlite_path).unwrap();
+    let sections: i64 = connection
+        .query_row("SELECT COUNT(DISTINCT section) FROM report", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert!(sections > 0);
+
+    assert_eq!(writes.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_lan_survey_duration() {
+    let results_dir = env::temp_dir().join("test_lan_survey_duration");
+    let mut config = local_config(&results_dir, LAN_SURVEY_PORT);
+    config.mode = Mode::LanSurvey;
+    config.operation_duration = 1;
+
+    // No peer ever shows up, the run still ends once its duration is covered
+    let mut controller = Controller::new(config).await.unwrap();
+    let result = timeout(Duration::from_secs(SLEEP), controller.run()).await;
+    assert!(result.unwrap().is_ok());
+    assert!(results_dir.join("network_report.txt").exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_handle_after_run() {
+    let results_dir = env::temp_dir().join("test_handle_after_run");
+    let mut config = local_config(&results_dir, HANDLE_AFTER_RUN_PORT);
+    config.mode = Mode::LanSurvey;
+    config.operation_duration = 1;
+
+    let mut controller = Controller::new(config).await.unwrap();
+    let handle = controller.handle();
+    assert!(controller.run().await.is_ok());
+
+    // The controller is still alive, but no longer answers
+    let answers = timeout(Duration::from_secs(SLEEP), async {
+        (
+            handle.snapshot().await.is_none(),
+            handle.state().await.is_none(),
+            handle.flush().await.is_none(),
+        )
+    })
+    .await;
+    assert_eq!(answers.unwrap(), (true, true, true));
+    drop(controller);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_history_db() {
+    let results_dir = env::temp_dir().join("test_history_db");
+    let history_path = results_dir.join("history.db");
+    let _ = fs::remove_file(&history_path);
+
+    let mut listener_config = local_config(&results_dir.join("listener"), HISTORY_LISTENER_PORT);
+    listener_config.mode = Mode::LanSurvey;
+    let listener_peer_id = listener_config.keypair.public().to_peer_id();
+    let mut listener = Controller::new(listener_config).await.unwrap();
+    let listener_handle = listener.handle();
+    let listener_task = tokio::spawn(async move { listener.run().await });
+
+    let mut dialer_config = local_config(&results_dir.join("dialer"), HISTORY_DIALER_PORT);
+    dialer_config.bootstrap_addr = vec![format!(
+        "/ip4/127.0.0.1/tcp/{}/p2p/{}",
+        HISTORY_LISTENER_PORT, listener_peer_id
+    )];
+    dialer_config.history_db = Some(history_path.display().to_string());
+    let mut dialer = Controller::new(dialer_config).await.unwrap();
+    let mut events = dialer.events();
+    let dialer_handle = dialer.handle();
+    let dialer_task = tokio::spawn(async move { dialer.run().await.map(|_| dialer) });
+
+    // Nothing listens on port 1, so this peer is unreachable
+    let unreachable_peer_id = PeerId::random();
+    dialer_handle.dial(
+        format!("/ip4/127.0.0.1/tcp/1/p2p/{}", unreachable_peer_id)
+            .parse()
+            .unwrap(),
+    );
+
+    let (mut identified, mut unreachable) = (false, false);
+    let _ = timeout(Duration::from_secs(SLEEP), async {
+        while let Some(event) = events.next().await {
+            match event {
+                ControllerEvent::PeerIdentified { peer_id, .. } if peer_id == listener_peer_id => {
+                    identified = true
+                }
+                ControllerEvent::PeerUnreachable { .. } => unreachable = true,
+                _ => {}
+            }
+            if identified && unreachable {
+                break;
+            }
+        }
+    })
+    .await;
+    assert!(identified && unreachable);
+
+    dialer_handle.stop();
+    listener_handle.stop();
+    let dialer = dialer_task.await.unwrap().unwrap();
+    assert!(listener_task.await.unwrap().is_ok());
+
+    // The run and both peers are in the history once the results are written
+    let history = dialer.history.as_ref().unwrap();
+    let listener_history = history.peer_history(&listener_peer_id.to_string()).unwrap();
+    assert!(listener_history.first_seen.is_some());
+    assert_eq!(listener_history.runs, 1);
+    assert_eq!(listener_history.agent_versions.len(), 1);
+    let unreachable_history = history
+        .peer_history(&unreachable_peer_id.to_string())
+        .unwrap();
+    assert_eq!(unreachable_history.unreachable, 1);
+    assert!(unreachable_history.first_seen.is_none());
+
+    let connection = rusqlite::Connection::open(&history_path).unwrap();
+    let ended_at: Option<i64> = connection
+        .query_row(
+            "SELECT ended_at FROM runs WHERE run_id = ?1",
+            [history.run_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(ended_at.is_some());
+
+    // The unreachable address is reported with RFC 3339 first-seen and last-seen times in UTC
+    let mut reader = Reader::from_path(results_dir.join("dialer/unreachable.csv")).unwrap();
+    assert_eq!(&reader.headers().unwrap()[2], "FirstSeen");
+    assert_eq!(&reader.headers().unwrap()[3], "LastSeen");
+    let record = reader.records().next().unwrap().unwrap();
+    let first_seen = DateTime::parse_from_rfc3339(&record[2]).unwrap();
+    let last_seen = DateTime::parse_from_rfc3339(&record[3]).unwrap();
+    assert!(record[2].ends_with('Z'));
+    assert!(first_seen <= last_seen);
+}
+
+// A custom sink that only counts how often it was written to
+struct CountingSink(Arc<AtomicUsize>);
+
+impl ReportSink for CountingSink {
+    fn name(&self) -> String {
+        String::from("counter")
+    }
+
+    fn write(&mut self, _: &ControllerState, _: &NetworkReport) -> Result<(), CLIError> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+// Send a bodyless HTTP request to the API under test and return the raw response
+async fn http_request(method: &str, path: &str) -> String {
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        method, path
+    );
+
+    tokio::task::spawn_blocking(move || {
+        let mut stream = TcpStream::connect(("127.0.0.1", API_PORT)).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    })
+    .await
+    .unwrap()
+}
+
+// State with one identified peer and one unreachable address, as a CSV sink gets it
+fn single_peer_state() -> ControllerState {
+    ControllerState {
+        status: RunStatus {
+            local_peer_id: String::from("local"),
+            mode: String::from("LanSurvey"),
+            uptime: 0,
+            remaining: 0,
+            connected_peers: 1,
+            discovered_peers: 1,
+            unreachable_addrs: 1,
+            pending_queries: 0,
+        },
+        peers: vec![PeerState {
+            peer_id: String::from("a"),
+            ip: String::from("172.16.3.2"),
+            first_seen: String::from("1"),
+            last_seen: String::from("1"),
+            discovery_source: None,
+            discovered_at: None,
+            discovered_by: None,
+            agent_version: None,
+        }],
+        unreachable: vec![UnreachableState {
+            address: String::from("/ip4/172.16.3.9/tcp/37000"),
+            status: String::from("Unreachable"),
+            first_seen: String::from("1"),
+            last_seen: String::from("1"),
+        }],
+        routing_table: Vec::new(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_preflight_check() {
+    let results_dir = env::temp_dir().join("test_preflight_check");
+
+    let mut listener_config = local_config(&results_dir.join("listener"), PREFLIGHT_LISTENER_PORT);
+    listener_config.mode = Mode::LanSurvey;
+    let listener_peer_id = listener_config.keypair.public().to_peer_id();
+    let mut listener = Controller::new(listener_config).await.unwrap();
+    let listener_handle = listener.handle();
+    let listener_task = tokio::spawn(async move { listener.run().await });
+    sleep(Duration::from_secs(1)).await;
+
+    // A healthy configuration passes without writing any report
+    let mut config = local_config(&results_dir.join("checker"), PREFLIGHT_CHECKER_PORT);
+    config.bootstrap_addr = vec![format!(
+        "/ip4/127.0.0.1/tcp/{}/p2p/{}",
+        PREFLIGHT_LISTENER_PORT, listener_peer_id
+    )];
+    let preflight = Preflight::run(&config, Duration::from_secs(SLEEP)).await;
+    assert!(preflight.passed(), "{}", preflight.to_report().render());
+    assert!(!Path::new(&config.just_connected).exists());
+
+    // Every problem is reported, the first one decides the exit code
+    config
+        .bootstrap_addr
+        .push(String::from("/ip4/127.0.0.1/udp/37000"));
+    config.just_connected = results_dir
+        .join("missing")
+        .join("newly_connected.csv")
+        .to_string_lossy()
+        .to_string();
+    config.listening_port = PREFLIGHT_LISTENER_PORT;
+    let preflight = Preflight::run(&config, Duration::from_secs(SLEEP)).await;
+    let failed: Vec<&str> = preflight
+        .checks
+        .iter()
+        .filter(|check| check.result.is_err())
+        .map(|check| check.section)
+        .collect();
+    assert_eq!(
+        failed,
+        vec!["Bootstrap Addresses", "Output Paths", "Listen Addresses"]
+    );
+    assert_eq!(preflight.into_result().unwrap_err().exit_code(), 2);
+
+    listener_handle.stop();
+    assert!(listener_task.await.unwrap().is_ok());
+}
+
+// Config for a controller on localhost that writes its results to `dir`
+fn local_config(dir: &Path, listening_port: u16) -> Config {
+    fs::create_dir_all(dir).unwrap();
+    let path = |file: &str| dir.join(file).to_string_lossy().to_string();
+
+    Config {
+        listening_port,
+        mdns: MdnsMode::Disabled,
+        just_connected: path("newly_connected.csv"),
+        unreachable_csv: path("unreachable.csv"),
+        topology_dot: path("topology.dot"),
+        topology_graphml: path("topology.graphml"),
+        network_report: path("network_report.txt"),
+        churn_csv: path("churn.csv"),
+        ..Config::default()
+    }
+}
+
 //////////////////////////////////////////////////////////////
 // Helpers for setting up/modifying a local network via Docker
 //////////////////////////////////////////////////////////////
@@ -257,6 +1407,19 @@ impl Drop for DockerCompose {
 ////////////////////////
 
 const SLEEP: u64 = 15;
+const LISTENER_PORT: u16 = 7201;
+const DIALER_PORT: u16 = 7202;
+const COMMAND_LISTENER_PORT: u16 = 7203;
+const COMMAND_DIALER_PORT: u16 = 7204;
+const API_LISTENING_PORT: u16 = 7205;
+const API_PORT: u16 = 7280;
+const SINKS_LISTENING_PORT: u16 = 7206;
+const HISTORY_LISTENER_PORT: u16 = 7207;
+const HISTORY_DIALER_PORT: u16 = 7208;
+const PREFLIGHT_LISTENER_PORT: u16 = 7209;
+const PREFLIGHT_CHECKER_PORT: u16 = 7210;
+const LAN_SURVEY_PORT: u16 = 7211;
+const HANDLE_AFTER_RUN_PORT: u16 = 7212;
 const OPERATION_DURATION: u64 = 181;
 const NETWORK_NAME: &str = "compose_net";
 const NEW_PEER_OBSERVED_IP: &str = "172.16.3.6";